@@ -0,0 +1,280 @@
+use crate::{
+    position::TextPosition,
+    unsaved_chunks::{UnsavedChunk, UnsavedChunks},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Default number of characters a single chunk has to add to be reported as a paste. Typing
+/// adds one character per chunk, but IME conversions, autocorrect and auto-completion also add a
+/// word or a few characters at once, so shorter additions are counted as typing
+pub const DEFAULT_PASTE_MIN_LENGTH: u64 = 16;
+
+/// Consecutive single character additions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TypingRun {
+    /// Index of the first chunk in the run
+    pub first_chunk: usize,
+    /// Index of the last chunk in the run
+    pub last_chunk: usize,
+    /// Position of the first typed character
+    pub position: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<TextPosition>,
+    pub keystrokes: usize,
+    pub text: String,
+}
+
+/// A single chunk adding at least the paste minimum length characters at once
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PasteEvent {
+    /// Index of the chunk
+    pub chunk: usize,
+    pub position: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<TextPosition>,
+    /// Number of characters pasted, surrogate pairs count as one character
+    pub length: u64,
+    /// Number of UTF-16 code units replaced by the paste
+    pub replaced: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InputEvent {
+    Typing(TypingRun),
+    Paste(PasteEvent),
+}
+
+/// Group the additions in `chunks` into typing runs and paste events. Chunks adding at least
+/// `paste_min_length` characters are pastes. A typing run continues while each chunk adds
+/// characters right after the previous one, deletions end the run
+pub fn classify_input(chunks: &UnsavedChunks, paste_min_length: u64) -> Vec<InputEvent> {
+    let mut events = vec![];
+    let mut run: Option<TypingRun> = None;
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let text = chunk.data().unwrap_or_default();
+        let length = text.chars().count() as u64;
+        if length >= paste_min_length {
+            events.extend(run.take().map(InputEvent::Typing));
+            events.push(InputEvent::Paste(PasteEvent {
+                chunk: index,
+                position: chunk.position(),
+                location: chunk.location().cloned(),
+                length,
+                replaced: chunk.num_of_deletion(),
+                text: text.to_string(),
+            }));
+            continue;
+        }
+
+        if chunk.num_of_addition() == 0 {
+            events.extend(run.take().map(InputEvent::Typing));
+            continue;
+        }
+
+        match run.as_mut() {
+            // Continue the run if the character is added right after the previous one without replacing a selection
+            Some(typing)
+                if chunk.num_of_deletion() == 0
+                    && chunk.position()
+                        == typing
+                            .position
+                            .saturating_add(typing.text.encode_utf16().count() as u64) =>
+            {
+                typing.last_chunk = index;
+                typing.keystrokes += 1;
+                typing.text.push_str(text);
+            }
+            _ => {
+                events.extend(run.take().map(InputEvent::Typing));
+                run = Some(TypingRun {
+                    first_chunk: index,
+                    last_chunk: index,
+                    position: chunk.position(),
+                    location: chunk.location().cloned(),
+                    keystrokes: 1,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+    events.extend(run.map(InputEvent::Typing));
+
+    events
+}
+
+/// Get only the paste events in `chunks`
+pub fn pastes(chunks: &UnsavedChunks, paste_min_length: u64) -> Vec<PasteEvent> {
+    classify_input(chunks, paste_min_length)
+        .into_iter()
+        .filter_map(|event| match event {
+            InputEvent::Paste(paste) => Some(paste),
+            InputEvent::Typing(_) => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EditKind {
+    Typed,
+    Deleted,
+    Replaced,
+}
+
+/// Adjacent unsaved chunks merged into a single edit
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct EditSession {
+    pub kind: EditKind,
+    /// Index of the first chunk in the session
+    pub first_chunk: usize,
+    /// Index of the last chunk in the session
+    pub last_chunk: usize,
+    /// Position of the start of the edited text
+    pub position: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<TextPosition>,
+    /// Text added by the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Number of characters deleted by the session
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub deleted: u64,
+    /// Text deleted by the session, recovered by replaying the chunks on top of the content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_text: Option<String>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl EditSession {
+    fn new(index: usize, chunk: &UnsavedChunk, deleted_text: Option<String>) -> Self {
+        let kind = match (chunk.num_of_deletion(), chunk.num_of_addition()) {
+            (0, _) => EditKind::Typed,
+            (_, 0) => EditKind::Deleted,
+            _ => EditKind::Replaced,
+        };
+        Self {
+            kind,
+            first_chunk: index,
+            last_chunk: index,
+            position: chunk.position(),
+            location: chunk.location().cloned(),
+            text: chunk.data().map(|data| data.to_string()),
+            deleted: chunk.num_of_deletion(),
+            deleted_text,
+        }
+    }
+
+    /// Position right after the text added by the session
+    fn end(&self) -> u64 {
+        self.position.saturating_add(
+            self.text
+                .as_deref()
+                .unwrap_or_default()
+                .encode_utf16()
+                .count() as u64,
+        )
+    }
+
+    /// Try to merge `chunk` into the session. Returns `false` if it isn't adjacent to the session
+    fn merge(&mut self, index: usize, chunk: &UnsavedChunk, deleted_text: &Option<String>) -> bool {
+        let is_insert = chunk.num_of_deletion() == 0 && chunk.num_of_addition() > 0;
+        let is_delete = chunk.num_of_deletion() > 0 && chunk.num_of_addition() == 0;
+
+        match self.kind {
+            // Typing continues right after the added text, also after replacing a selection
+            EditKind::Typed | EditKind::Replaced if is_insert && chunk.position() == self.end() => {
+                self.text
+                    .get_or_insert_with(String::new)
+                    .push_str(chunk.data().unwrap_or_default());
+            }
+            // Backspace, deletes the characters before the session position
+            EditKind::Deleted
+                if is_delete
+                    && chunk.position().checked_add(chunk.num_of_deletion())
+                        == Some(self.position) =>
+            {
+                self.position = chunk.position();
+                self.location = chunk.location().cloned();
+                self.deleted = self.deleted.saturating_add(chunk.num_of_deletion());
+                self.deleted_text = match (deleted_text, &self.deleted_text) {
+                    (Some(new), Some(old)) => Some(format!("{}{}", new, old)),
+                    _ => None,
+                };
+            }
+            // Delete key, deletes the characters after the session position
+            EditKind::Deleted if is_delete && chunk.position() == self.position => {
+                self.deleted = self.deleted.saturating_add(chunk.num_of_deletion());
+                self.deleted_text = match (&self.deleted_text, deleted_text) {
+                    (Some(old), Some(new)) => Some(format!("{}{}", old, new)),
+                    _ => None,
+                };
+            }
+            _ => return false,
+        }
+        self.last_chunk = index;
+        true
+    }
+}
+
+fn chars(count: u64) -> String {
+    match count {
+        1 => "1 char".to_string(),
+        x => format!("{} chars", x),
+    }
+}
+
+impl Display for EditSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = self.text.as_deref().unwrap_or_default();
+        match self.kind {
+            EditKind::Typed => write!(f, "typed {:?}", text)?,
+            EditKind::Deleted => write!(f, "deleted {}", chars(self.deleted))?,
+            EditKind::Replaced => write!(f, "replaced {} with {:?}", chars(self.deleted), text)?,
+        }
+        if let Some(deleted_text) = &self.deleted_text {
+            write!(f, " ({:?})", deleted_text)?;
+        }
+        match &self.location {
+            Some(location) => write!(f, " at {}", location),
+            None => write!(f, " at offset {}", self.position),
+        }
+    }
+}
+
+/// Merge adjacent inserts and deletions in `chunks` into edit sessions. If `content` is the text
+/// the chunks are applied to, the deleted text is recovered by replaying the chunks
+pub fn edit_sessions(chunks: &UnsavedChunks, content: Option<&str>) -> Vec<EditSession> {
+    let deleted_texts = content
+        .map(|content| chunks.replay_mapped(content, None, None).deleted_texts)
+        .unwrap_or_default();
+    replayed_edit_sessions(chunks, &deleted_texts)
+}
+
+/// Same as `edit_sessions` with the text deleted by each chunk already recovered
+pub(crate) fn replayed_edit_sessions(
+    chunks: &UnsavedChunks,
+    deleted_texts: &[Option<String>],
+) -> Vec<EditSession> {
+    let mut sessions: Vec<EditSession> = vec![];
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let deleted_text = deleted_texts.get(index).cloned().flatten();
+        let merged = match sessions.last_mut() {
+            Some(session) => session.merge(index, chunk, &deleted_text),
+            None => false,
+        };
+        if !merged {
+            sessions.push(EditSession::new(index, chunk, deleted_text));
+        }
+    }
+
+    sessions
+}
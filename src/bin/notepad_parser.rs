@@ -1,17 +1,21 @@
-use clap::{value_parser, Arg, Command};
-use csv::WriterBuilder;
+use clap::{value_parser, Arg, ArgAction, Command};
 use glob::glob;
 use notepad_parser::{
-    enums::{CRType, Encoding},
     errors::NotepadErrors,
+    lint::LintSeverity,
+    output::{DiffSink, OutputSink, SinkOptions, SinkRegistry},
+    package::NotepadPackage,
+    research::UndecodedFieldsStats,
+    schema::record_schemas,
+    settings::{settings_path_for_tabstate, AppSettings, NotepadSettings},
+    summary::TabStateSummary,
     NotepadTabStat,
 };
-use serde::Serialize;
-use serde_json;
 use std::{
-    convert::From,
+    collections::{BTreeMap, HashMap},
     fs::File,
-    io::{self, Write},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
     process::exit,
 };
 
@@ -23,76 +27,151 @@ use log4rs::{
     Config,
 };
 
-use winparsingtools::date_time::FileTime;
-
-enum OutputFormat {
-    JSONL,
-    CSV,
+/// Parse the `--csv-delimiter` value
+fn parse_delimiter(value: &str) -> std::result::Result<u8, String> {
+    match value {
+        "tab" | "\\t" => Ok(b'\t'),
+        value if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        value => Err(format!(
+            "Expected a single ASCII character or 'tab', found '{}'",
+            value
+        )),
+    }
 }
 
-impl From<&str> for OutputFormat {
-    fn from(value: &str) -> Self {
-        match value {
-            "jsonl" => OutputFormat::JSONL,
-            "csv" => OutputFormat::CSV,
-            _ => OutputFormat::JSONL,
+/// Group the parsed records by their `TabState` directory and write one `TabStateSummary` per directory
+fn write_summaries(sink: &mut dyn OutputSink, records: BTreeMap<String, Vec<NotepadTabStat>>) {
+    for (dir, tabs) in records.iter() {
+        let summary = TabStateSummary::from_records(dir, tabs);
+        match sink.summary(&summary) {
+            Ok(_) => debug!(
+                "Successfully writen the summary for '{}'",
+                summary.tabstate_dir
+            ),
+            Err(e) => error!(
+                "Error while writing the summary for '{}', ERROR: {}",
+                summary.tabstate_dir, e
+            ),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-struct CsvRecord {
-    tabstate_path: Option<String>,
-    is_saved_file: bool,
-    path_size: u64,
-    path: Option<String>,
-    file_size: Option<u64>,
-    encoding: Option<Encoding>,
-    cr_type: Option<CRType>,
-    last_write_time: Option<FileTime>,
-    file_hash: Option<String>,
-    cursor_start: Option<u64>,
-    cursor_end: Option<u64>,
-    word_wrap: bool,
-    rtl: bool,
-    show_unicode: bool,
-    version: u64,
-    file_content_size: u64,
-    file_content: String,
-    contain_unsaved_data: bool,
-    checksum: String,
-    unsaved_chunks_str: Option<String>,
-    raw: String,
+/// Flush the output at the end of the run
+fn finish_output(sink: &mut dyn OutputSink, output_path: &str) {
+    if let Err(e) = sink.finish() {
+        error!(
+            "Unable to finish the output '{}', ERROR: {}",
+            output_path, e
+        );
+    }
+}
+
+/// Write the JSON Schema of `record_type` or of all the record types to `output`
+fn write_schemas(output: &mut dyn Write, record_type: Option<&String>) {
+    let schemas = record_schemas();
+
+    let json = match record_type {
+        Some(record_type) => match schemas.get(record_type.as_str()) {
+            Some(schema) => serde_json::to_string_pretty(schema),
+            None => {
+                error!("Unknown record type '{}'", record_type);
+                return;
+            }
+        },
+        None => serde_json::to_string_pretty(&schemas),
+    };
+    match json {
+        Ok(json) => {
+            if let Err(e) = writeln!(output, "{}", json) {
+                error!("Unable to write the schema, ERROR: {}", e);
+            }
+        }
+        Err(e) => error!(
+            "{}",
+            NotepadErrors::CLIError(
+                e.to_string(),
+                "Unable to convert the schema to JSON".to_string()
+            )
+        ),
+    }
 }
 
-impl From<NotepadTabStat> for CsvRecord {
-    fn from(value: NotepadTabStat) -> Self {
-        let json_data = match serde_json::to_string(&value) {
-            Ok(data) => data,
-            Err(e) => e.to_string(),
+/// Read the records from the JSONL files matching `path` and write them to `sink`
+fn convert_records(sink: &mut dyn OutputSink, path: &str, output_path: &str) {
+    let paths = match glob(path) {
+        Ok(paths) => paths,
+        Err(e) => {
+            error!("Invalid glob pattern '{}', ERROR: {}", path, e);
+            return;
+        }
+    };
+
+    for input_path in paths.flatten() {
+        let input_path = input_path.to_string_lossy().to_string();
+        let input = match File::open(&input_path) {
+            Ok(f) => BufReader::new(f),
+            Err(e) => {
+                error!(
+                    "{}",
+                    NotepadErrors::FileOpen(e.to_string(), input_path.to_string())
+                );
+                continue;
+            }
         };
-        Self {
-            tabstate_path: value.tabstate_path,
-            is_saved_file: value.is_saved_file,
-            path_size: value.path_size,
-            path: value.path,
-            file_size: value.file_size,
-            encoding: value.encoding,
-            cr_type: value.cr_type,
-            last_write_time: value.last_write_time,
-            file_hash: value.file_hash,
-            cursor_start: value.cursor_start,
-            cursor_end: value.cursor_end,
-            word_wrap: value.config_block.word_wrap,
-            rtl: value.config_block.rtl,
-            show_unicode: value.config_block.show_unicode,
-            version: value.config_block.version,
-            file_content_size: value.file_content_size,
-            file_content: value.file_content,
-            contain_unsaved_data: value.contain_unsaved_data,
-            checksum: value.checksum,
-            unsaved_chunks_str: value.unsaved_chunks_str,
-            raw: json_data,
+        for (index, line) in input.lines().enumerate() {
+            let source = format!("{}:{}", input_path, index + 1);
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("Unable to read the line '{}', ERROR: {}", source, e);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<NotepadTabStat>(&line) {
+                Ok(mut data) => {
+                    data.update_reconstructed_content();
+                    match sink.record(&data) {
+                        Ok(_) => debug!(
+                            "Successfully writen the record '{}' to '{}'",
+                            source, output_path
+                        ),
+                        Err(e) => error!(
+                            "Unable to write the record '{}' to '{}', ERROR: {}",
+                            source, output_path, e
+                        ),
+                    }
+                }
+                Err(e) => error!(
+                    "{}",
+                    NotepadErrors::CLIError(
+                        e.to_string(),
+                        format!("Unable to parse the record '{}'", source)
+                    )
+                ),
+            }
+        }
+    }
+}
+
+/// Parse the `settings.dat` hive at `path` and extract the Notepad settings
+fn load_settings(path: &Path) -> Option<NotepadSettings> {
+    match AppSettings::from_path(&path.to_string_lossy()) {
+        Ok(settings) => {
+            debug!("Successfully parsed the settings file '{}'", path.display());
+            Some(settings.notepad_settings())
+        }
+        Err(e) => {
+            error!(
+                "{}",
+                NotepadErrors::CLIError(
+                    e.to_string(),
+                    format!("Unable to parse the settings file '{}'", path.display())
+                )
+            );
+            None
         }
     }
 }
@@ -119,6 +198,7 @@ fn init_logger(level: log::LevelFilter) -> log4rs::Handle {
 }
 
 fn main() {
+    let sink_registry = SinkRegistry::default();
     let cli = Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author("AbdulRhman Alfaifi <aalfaifi@u0041.co>")
@@ -149,8 +229,9 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
                 .long("output-format")
                 .value_name("FORMAT")
                 .help("Specifiy the output format")
-                .value_parser(["jsonl", "csv"])
-                .default_value("jsonl"),
+                .value_parser(sink_registry.names())
+                .default_value("jsonl")
+                .global(true),
         )
         .arg(
             Arg::new("output-path")
@@ -159,7 +240,87 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
                 .value_name("FILE")
                 .help("Specifiy the output file")
                 .value_parser(value_parser!(String))
-                .default_value("stdout"),
+                .default_value("stdout")
+                .global(true),
+        )
+        .arg(
+            Arg::new("csv-delimiter")
+                .long("csv-delimiter")
+                .value_name("CHAR")
+                .help("Delimiter for the CSV output, a single ASCII character or 'tab'")
+                .value_parser(parse_delimiter)
+                .default_value(",")
+                .global(true),
+        )
+        .arg(
+            Arg::new("safe-csv")
+                .long("safe-csv")
+                .help("Neutralize values starting with a formula character (=, +, -, @, tab) and escape backslashes and line breaks in the CSV output so it is safe to open in a spreadsheet")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("csv-bom")
+                .long("csv-bom")
+                .help("Write a UTF-8 BOM at the start of the CSV output so spreadsheets detect the encoding")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("context-size")
+                .long("context-size")
+                .value_name("CHARS")
+                .help("Number of characters to include on each side of the cursor in cursor_context [default: 32]")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("paste-min-length")
+                .long("paste-min-length")
+                .value_name("CHARS")
+                .help("Number of characters an unsaved chunk has to add to be reported as a paste [default: 16]")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("notepad-package")
+                .long("notepad-package")
+                .value_name("PATH")
+                .help("Detect the installed Notepad version from an evidence root, the package folder or its AppxManifest.xml and add it to each record")
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("settings")
+                .long("settings")
+                .value_name("FILE")
+                .help("Add the Notepad app settings from settings.dat to each record. If FILE isn't specified, it is located relative to each TabState file")
+                .value_parser(value_parser!(String))
+                .num_args(0..=1)
+                .default_missing_value("auto"),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .value_name("MODE")
+                .help("Print the diff between the saved content and the unsaved buffer of each tab instead of the records")
+                .value_parser(["unified", "word"]),
+        )
+        .arg(
+            Arg::new("summary")
+                .long("summary")
+                .help("Group the tabs by their TabState directory and output one summary record per directory, the TabState files don't record which window a tab belongs to")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("research")
+                .long("research")
+                .help("Include the undecoded fields and their offsets in the output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("research-stats")
+                .long("research-stats")
+                .value_name("FILE")
+                .help("Write a frequency table of the undecoded fields values across all the parsed files to FILE")
+                .value_parser(value_parser!(String)),
         )
         .arg(
             Arg::new("log-level")
@@ -167,8 +328,30 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
                 .long("log-level")
                 .value_name("LEVEL")
                 .help("Level for logs")
-                .value_parser(["trace", "debug", "info", "error", "quiet"])
-                .default_value("quiet"),
+                .value_parser(["trace", "debug", "info", "warn", "error", "quiet"])
+                .default_value("quiet")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Print the JSON Schema of the output records")
+                .arg(
+                    Arg::new("record-type")
+                        .value_name("TYPE")
+                        .help("Print only the schema of this record type, otherwise print all of them keyed by the record type")
+                        .value_parser(["tab", "research", "summary", "csv", "csv-chunks", "summary-csv"]),
+                ),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert the JSONL output of an earlier run to the output format without the TabState files")
+                .arg(
+                    Arg::new("input-file")
+                        .value_name("FILE")
+                        .help("Path to the JSONL files to convert. Accepts glob.")
+                        .required(true)
+                        .value_parser(value_parser!(String)),
+                ),
         )
         .get_matches();
 
@@ -177,8 +360,8 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
         None => "C:\\Users\\*\\AppData\\Local\\Packages\\Microsoft.WindowsNotepad_8wekyb3d8bbwe\\LocalState\\TabState\\*.bin"
     };
     let output_format = match cli.get_one::<String>("output-format") {
-        Some(format) => OutputFormat::from(format.to_owned().as_str()),
-        None => OutputFormat::from("jsonl"),
+        Some(format) => format.as_str(),
+        None => "jsonl",
     };
 
     let mut output_path = "stdout".to_string();
@@ -211,14 +394,92 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
         "trace" => log::LevelFilter::Trace,
         "debug" => log::LevelFilter::Debug,
         "info" => log::LevelFilter::Info,
+        "warn" => log::LevelFilter::Warn,
         "error" => log::LevelFilter::Error,
         _ => log::LevelFilter::Off,
     };
 
     init_logger(log_level);
 
-    let mut csv_headers_printed = false;
-    // if let OutputFormat::CSV = output_format {}
+    if let Some(("schema", schema)) = cli.subcommand() {
+        write_schemas(&mut output, schema.get_one::<String>("record-type"));
+        return;
+    }
+
+    let research = cli.get_flag("research");
+    let diff_mode = cli.get_one::<String>("diff");
+    let sink_options = SinkOptions {
+        // Records converted from JSONL keep the undecoded fields if they were written with `--research`
+        research: research || matches!(cli.subcommand(), Some(("convert", _))),
+        csv_delimiter: cli.get_one::<u8>("csv-delimiter").copied().unwrap_or(b','),
+        safe_csv: cli.get_flag("safe-csv"),
+        csv_bom: cli.get_flag("csv-bom"),
+        output_path: match output_path.as_str() {
+            "stdout" => None,
+            path => Some(PathBuf::from(path)),
+        },
+    };
+    // Diffs are plain text whatever the output format is
+    let mut sink: Box<dyn OutputSink> = match diff_mode.map(|mode| mode.as_str()) {
+        Some(mode) => Box::new(DiffSink::new(output, mode == "word")),
+        None => match sink_registry.create(output_format, output, &sink_options) {
+            Ok(sink) => sink,
+            Err(e) => {
+                error!("{}. Exiting...", e);
+                exit(1);
+            }
+        },
+    };
+    if let Err(e) = sink.begin() {
+        error!("Unable to start the output '{}', ERROR: {}", output_path, e);
+    }
+
+    if let Some(("convert", convert)) = cli.subcommand() {
+        if let Some(path) = convert.get_one::<String>("input-file") {
+            convert_records(sink.as_mut(), path, &output_path);
+        }
+        finish_output(sink.as_mut(), &output_path);
+        return;
+    }
+
+    let research_stats_path = cli.get_one::<String>("research-stats");
+    let mut research_stats = UndecodedFieldsStats::new();
+
+    let notepad_version = match cli.get_one::<String>("notepad-package") {
+        Some(package_path) => match NotepadPackage::detect(package_path) {
+            Ok(package) => {
+                info!(
+                    "Detected Notepad version '{}' from '{}'",
+                    package.version, package.source
+                );
+                Some(package.version)
+            }
+            Err(e) => {
+                error!(
+                    "{}",
+                    NotepadErrors::CLIError(
+                        e.to_string(),
+                        format!(
+                            "Unable to detect the Notepad version from '{}'",
+                            package_path
+                        )
+                    )
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let context_size = cli.get_one::<usize>("context-size").copied();
+    let paste_min_length = cli.get_one::<u64>("paste-min-length").copied();
+
+    let settings_path = cli.get_one::<String>("settings");
+    let mut settings_cache: HashMap<PathBuf, Option<NotepadSettings>> = HashMap::new();
+
+    // Diffs are written per tab
+    let summary = cli.get_flag("summary") && diff_mode.is_none();
+    let mut summaries: BTreeMap<String, Vec<NotepadTabStat>> = BTreeMap::new();
 
     for entry in glob(path).expect("Failed to read glob pattern") {
         match entry {
@@ -234,91 +495,53 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
                     }
                 };
                 match NotepadTabStat::from_path(path_str) {
-                    Ok(data) => match output_format {
-                        OutputFormat::JSONL => match serde_json::to_string(&data) {
-                            Ok(json) => match write!(output, "{}\n", json) {
-                                Ok(_) => debug!(
-                                    "Successfully writen JSON data for the file '{}'",
-                                    path_str
-                                ),
-                                Err(e) => error!(
-                                    "Error while writing the JSON data for the file '{}', ERROR: {}",
-                                    path_str,
-                                    e
-                                ),
-                            },
-                            Err(e) => {
-                                error!(
-                                    "{}",
-                                    NotepadErrors::CLIError(
-                                        e.to_string(),
-                                        format!(
-                                            "Unable to convert results to JSON for the file '{}'",
-                                            path_str
-                                        )
-                                    )
-                                );
-                            }
-                        },
-                        OutputFormat::CSV => {
-                            let mut csv_writer = WriterBuilder::new();
-                            let mut csv_writer_builder;
-                            if csv_headers_printed {
-                                csv_writer_builder =
-                                    csv_writer.has_headers(false).from_writer(vec![]);
-                            } else {
-                                csv_writer_builder =
-                                    csv_writer.has_headers(true).from_writer(vec![]);
-                                csv_headers_printed = true;
+                    Ok(mut data) => {
+                        for warning in data.warnings.iter().flatten() {
+                            match warning.code.severity() {
+                                LintSeverity::Info => info!("'{}': {}", path_str, warning),
+                                LintSeverity::Warning => warn!("'{}': {}", path_str, warning),
                             }
-
-                            let csv_record = CsvRecord::from(data);
-                            match csv_writer_builder.serialize(csv_record) {
-                                Ok(_) => debug!(
-                                    "Successfuly serilized CSV row for the file '{}'",
-                                    path_str
-                                ),
-                                Err(e) => error!(
-                                    "Unable to write CSV row, ERROR: {}, PATH: '{}'",
-                                    e, path_str
-                                ),
-                            }
-                            match csv_writer_builder.flush() {
-                                Ok(_) => trace!(
-                                    "Susseccfuly flushed the CSV record for the file '{}'",
-                                    path_str
-                                ),
-                                Err(e) => error!(
-                                    "Unable to flush CSV record, ERROR: {}, PATH: '{}'",
-                                    e, path_str
-                                ),
-                            }
-
-                            let row = match csv_writer_builder.into_inner() {
-                                Ok(bytes) => match String::from_utf8(bytes) {
-                                    Ok(r) => r,
-                                    Err(e) => {
-                                        error!("Unable to convert CSV writer buffer to String, ERROR: {}", e);
-                                        continue;
-                                    }
-                                },
-                                Err(e) => {
-                                    error!("Unable to convert CSV writer to String, ERROR: {}", e);
-                                    continue;
-                                }
+                        }
+                        data.notepad_version = notepad_version.clone();
+                        if let Some(context_size) = context_size {
+                            data.update_cursor_text(context_size);
+                        }
+                        if let Some(paste_min_length) = paste_min_length {
+                            data.update_pastes(paste_min_length);
+                        }
+                        if let Some(settings_path) = settings_path {
+                            let settings_path = match settings_path.as_str() {
+                                "auto" => settings_path_for_tabstate(path_str),
+                                path => Some(PathBuf::from(path)),
                             };
-                            match write!(output, "{}", row) {
-                                Ok(_) => debug!(
-                                    "Successfully writen the CSV row for file '{}' to '{}'",
-                                    path_str, output_path
-                                ),
-                                Err(e) => error!(
-                                    "Unable to write the CSV row for file '{}' to '{}', ERROR: {}",
-                                    path_str, output_path, e
-                                ),
+                            if let Some(settings_path) = settings_path {
+                                data.app_settings = settings_cache
+                                    .entry(settings_path.clone())
+                                    .or_insert_with(|| load_settings(&settings_path))
+                                    .clone();
+                            }
+                        }
+                        if research_stats_path.is_some() {
+                            research_stats.add(&data);
+                        }
+                        if summary {
+                            let dir = Path::new(path_str)
+                                .parent()
+                                .map(|p| p.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            summaries.entry(dir).or_default().push(data);
+                            continue;
+                        }
+                        match sink.record(&data) {
+                            Ok(_) => {
+                                debug!("Successfully writen the record for the file '{}'", path_str)
                             }
+                            Err(e) => error!(
+                                "Error while writing the record for the file '{}', ERROR: {}",
+                                path_str, e
+                            ),
                         }
-                    },
+                    }
                     Err(e) => {
                         error!(
                             "{}",
@@ -327,10 +550,39 @@ Reference: https://u0041.co/posts/articals/exploring-windows-artifacts-notepad-f
                                 format!("Unable to parse the file '{}'", path_str)
                             )
                         );
+                        if let Err(e) = sink.error(path_str, &e) {
+                            error!(
+                                "Unable to write the error for the file '{}', ERROR: {}",
+                                path_str, e
+                            );
+                        }
                     }
                 }
             }
             Err(e) => eprintln!("{:?}", e),
         }
     }
+    if summary {
+        write_summaries(sink.as_mut(), summaries);
+    }
+    finish_output(sink.as_mut(), &output_path);
+
+    if let Some(stats_path) = research_stats_path {
+        match File::create(stats_path) {
+            Ok(f) => match serde_json::to_writer_pretty(f, &research_stats) {
+                Ok(_) => debug!(
+                    "Successfully writen the undecoded fields stats to '{}'",
+                    stats_path
+                ),
+                Err(e) => error!(
+                    "Unable to write the undecoded fields stats to '{}', ERROR: {}",
+                    stats_path, e
+                ),
+            },
+            Err(e) => error!(
+                "Unable create the research stats file '{}', ERROR: {}",
+                stats_path, e
+            ),
+        }
+    }
 }
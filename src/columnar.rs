@@ -0,0 +1,993 @@
+use crate::{
+    analysis::{EditSession, PasteEvent},
+    diff::{DiffHunk, DiffLine},
+    errors::NotepadErrors,
+    lint::LintWarning,
+    output::{enum_text, OutputSink, SinkOptions},
+    position::{CursorText, TextPosition},
+    schema::SCHEMA_VERSION,
+    settings::{AppSettingData, NotepadSettings},
+    summary::TabStateSummary,
+    NotepadTabStat,
+};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, ListArray, RecordBatch, StringArray,
+    StructArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use std::{io::Write, mem, sync::Arc};
+use winparsingtools::date_time::FileTime;
+
+/// Number of records buffered before they are written as a record batch. Each batch is a
+/// separate Parquet row group so memory stays bounded on large runs
+pub const BATCH_SIZE: usize = 1024;
+
+/// Size of the buffered text after which the batch is written even if it has fewer than
+/// `BATCH_SIZE` records. Every row has the whole content of its tab, a batch of large tabs
+/// would otherwise buffer hundreds of MB
+pub const BATCH_BYTES: usize = 64 * 1024 * 1024;
+
+fn columnar_error(e: impl ToString, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// `FileTime` as a UTC timestamp with microseconds
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+}
+
+fn timestamp(time: Option<&FileTime>) -> Option<i64> {
+    time.map(|time| DateTime::<Utc>::from(time).timestamp_micros())
+}
+
+fn position_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("utf16_offset", DataType::UInt64, false),
+        Field::new("char_index", DataType::UInt64, false),
+        Field::new("utf8_offset", DataType::UInt64, false),
+        Field::new("line", DataType::UInt64, false),
+        Field::new("column", DataType::UInt64, false),
+        Field::new("file_offset", DataType::UInt64, true),
+    ])
+}
+
+fn cursor_text_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("saved", DataType::Utf8, true),
+        Field::new("reconstructed", DataType::Utf8, true),
+    ])
+}
+
+/// `AppSettingData` with a child for each variant, only the child of the value's variant is set
+fn setting_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("bool", DataType::Boolean, true),
+        Field::new("int", DataType::Int64, true),
+        Field::new("uint", DataType::UInt64, true),
+        Field::new("float", DataType::Float64, true),
+        Field::new("string", DataType::Utf8, true),
+        Field::new("raw", DataType::Utf8, true),
+    ])
+}
+
+fn app_settings_fields() -> Fields {
+    let setting = |name: &str| Field::new(name, DataType::Struct(setting_fields()), true);
+    Fields::from(vec![
+        setting("font_family"),
+        setting("font_size"),
+        setting("font_style"),
+        setting("theme"),
+        setting("open_file"),
+        setting("session_restore"),
+        setting("word_wrap"),
+    ])
+}
+
+fn chunk_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("position", DataType::UInt64, false),
+        Field::new("num_of_deletion", DataType::UInt64, false),
+        Field::new("num_of_addition", DataType::UInt64, false),
+        Field::new("data", DataType::Utf8, true),
+        Field::new("checksum", DataType::Utf8, false),
+        Field::new("checksum_valid", DataType::Boolean, true),
+        Field::new("location", DataType::Struct(position_fields()), true),
+    ])
+}
+
+fn edit_session_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("first_chunk", DataType::UInt64, false),
+        Field::new("last_chunk", DataType::UInt64, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new("location", DataType::Struct(position_fields()), true),
+        Field::new("text", DataType::Utf8, true),
+        Field::new("deleted", DataType::UInt64, false),
+        Field::new("deleted_text", DataType::Utf8, true),
+    ])
+}
+
+fn diff_line_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("tag", DataType::Utf8, false),
+        Field::new("text", DataType::Utf8, false),
+        Field::new("no_newline_at_end", DataType::Boolean, false),
+    ])
+}
+
+fn diff_hunk_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("old_start", DataType::UInt64, false),
+        Field::new("old_lines", DataType::UInt64, false),
+        Field::new("new_start", DataType::UInt64, false),
+        Field::new("new_lines", DataType::UInt64, false),
+        Field::new(
+            "lines",
+            list_type(DataType::Struct(diff_line_fields())),
+            false,
+        ),
+    ])
+}
+
+fn paste_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("chunk", DataType::UInt64, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new("location", DataType::Struct(position_fields()), true),
+        Field::new("length", DataType::UInt64, false),
+        Field::new("replaced", DataType::UInt64, false),
+        Field::new("text", DataType::Utf8, false),
+    ])
+}
+
+fn warning_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("code", DataType::Utf8, false),
+        Field::new("field", DataType::Utf8, false),
+        Field::new("message", DataType::Utf8, false),
+    ])
+}
+
+fn undecoded_field_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("field", DataType::Utf8, false),
+        Field::new("offset", DataType::UInt64, false),
+        Field::new("value", DataType::Utf8, false),
+    ])
+}
+
+fn list_type(item: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("item", item, true)))
+}
+
+/// Arrow schema of the parsed records. The nested fields of the records are structs, and the
+/// unsaved chunks, edit sessions, diff hunks, pastes and warnings are lists of structs that can
+/// be flattened with `UNNEST` in DuckDB or `explode` in Spark
+pub fn tab_schema(research: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("schema_version", DataType::Utf8, false),
+        Field::new("tabstate_path", DataType::Utf8, true),
+        Field::new("tab_guid", DataType::Utf8, true),
+        Field::new("tabstate_modified", timestamp_type(), true),
+        Field::new("tabstate_accessed", timestamp_type(), true),
+        Field::new("tabstate_changed", timestamp_type(), true),
+        Field::new("tabstate_created", timestamp_type(), true),
+        Field::new("tabstate_size", DataType::UInt64, true),
+        Field::new("notepad_version", DataType::Utf8, true),
+        Field::new("seq_number", DataType::UInt64, false),
+        Field::new("is_saved_file", DataType::Boolean, false),
+        Field::new("path_size", DataType::UInt64, false),
+        Field::new("path", DataType::Utf8, true),
+        Field::new("file_size", DataType::UInt64, true),
+        Field::new("encoding", DataType::Utf8, true),
+        Field::new("cr_type", DataType::Utf8, true),
+        Field::new("last_write_time", timestamp_type(), true),
+        Field::new("file_hash", DataType::Utf8, true),
+        Field::new("cursor_start", DataType::UInt64, true),
+        Field::new("cursor_end", DataType::UInt64, true),
+        Field::new(
+            "cursor_start_location",
+            DataType::Struct(position_fields()),
+            true,
+        ),
+        Field::new(
+            "cursor_end_location",
+            DataType::Struct(position_fields()),
+            true,
+        ),
+        Field::new(
+            "selected_text",
+            DataType::Struct(cursor_text_fields()),
+            true,
+        ),
+        Field::new(
+            "cursor_context",
+            DataType::Struct(cursor_text_fields()),
+            true,
+        ),
+        Field::new("word_wrap", DataType::Boolean, false),
+        Field::new("rtl", DataType::Boolean, false),
+        Field::new("show_unicode", DataType::Boolean, false),
+        Field::new("version", DataType::UInt64, false),
+        Field::new(
+            "app_settings",
+            DataType::Struct(app_settings_fields()),
+            true,
+        ),
+        Field::new("file_content_size", DataType::UInt64, false),
+        Field::new("file_content", DataType::Utf8, false),
+        Field::new("contain_unsaved_data", DataType::Boolean, false),
+        Field::new("checksum", DataType::Utf8, false),
+        Field::new(
+            "unsaved_chunks",
+            list_type(DataType::Struct(chunk_fields())),
+            true,
+        ),
+        Field::new("reconstructed_content", DataType::Utf8, true),
+        Field::new(
+            "edit_sessions",
+            list_type(DataType::Struct(edit_session_fields())),
+            true,
+        ),
+        Field::new(
+            "diff_hunks",
+            list_type(DataType::Struct(diff_hunk_fields())),
+            true,
+        ),
+        Field::new("pastes", list_type(DataType::Struct(paste_fields())), true),
+        Field::new(
+            "warnings",
+            list_type(DataType::Struct(warning_fields())),
+            true,
+        ),
+    ];
+    if research {
+        fields.push(Field::new(
+            "undecoded_fields",
+            list_type(DataType::Struct(undecoded_field_fields())),
+            false,
+        ));
+    }
+    Schema::new(fields)
+}
+
+/// Column values of the buffered records
+#[derive(Default)]
+struct TabColumns {
+    rows: usize,
+    /// Size of the buffered contents, chunks data and edit sessions text
+    bytes: usize,
+    tabstate_path: Vec<Option<String>>,
+    tab_guid: Vec<Option<String>>,
+    tabstate_modified: Vec<Option<i64>>,
+    tabstate_accessed: Vec<Option<i64>>,
+    tabstate_changed: Vec<Option<i64>>,
+    tabstate_created: Vec<Option<i64>>,
+    tabstate_size: Vec<Option<u64>>,
+    notepad_version: Vec<Option<String>>,
+    seq_number: Vec<u64>,
+    is_saved_file: Vec<bool>,
+    path_size: Vec<u64>,
+    path: Vec<Option<String>>,
+    file_size: Vec<Option<u64>>,
+    encoding: Vec<Option<String>>,
+    cr_type: Vec<Option<String>>,
+    last_write_time: Vec<Option<i64>>,
+    file_hash: Vec<Option<String>>,
+    cursor_start: Vec<Option<u64>>,
+    cursor_end: Vec<Option<u64>>,
+    cursor_start_location: Vec<Option<TextPosition>>,
+    cursor_end_location: Vec<Option<TextPosition>>,
+    selected_text: Vec<Option<CursorText>>,
+    cursor_context: Vec<Option<CursorText>>,
+    word_wrap: Vec<bool>,
+    rtl: Vec<bool>,
+    show_unicode: Vec<bool>,
+    version: Vec<u64>,
+    app_settings: Vec<Option<NotepadSettings>>,
+    file_content_size: Vec<u64>,
+    file_content: Vec<String>,
+    contain_unsaved_data: Vec<bool>,
+    checksum: Vec<String>,
+    /// Number of chunks of each record, `None` if the record has no chunks list
+    chunks: Vec<Option<usize>>,
+    chunk_position: Vec<u64>,
+    chunk_num_of_deletion: Vec<u64>,
+    chunk_num_of_addition: Vec<u64>,
+    chunk_data: Vec<Option<String>>,
+    chunk_checksum: Vec<String>,
+    chunk_checksum_valid: Vec<Option<bool>>,
+    chunk_location: Vec<Option<TextPosition>>,
+    reconstructed_content: Vec<Option<String>>,
+    edit_sessions: Vec<Option<Vec<EditSession>>>,
+    diff_hunks: Vec<Option<Vec<DiffHunk>>>,
+    pastes: Vec<Option<Vec<PasteEvent>>>,
+    warnings: Vec<Option<Vec<LintWarning>>>,
+    undecoded_fields: Vec<Option<usize>>,
+    undecoded_field: Vec<String>,
+    undecoded_offset: Vec<u64>,
+    undecoded_value: Vec<String>,
+}
+
+/// Offsets and validity of a list column from the number of items of each row
+fn list_array(field: &Field, lengths: &[Option<usize>], values: ArrayRef) -> ListArray {
+    let item = match field.data_type() {
+        DataType::List(item) => item.clone(),
+        _ => Arc::new(Field::new("item", values.data_type().clone(), true)),
+    };
+    let offsets = OffsetBuffer::from_lengths(lengths.iter().map(|len| len.unwrap_or_default()));
+    ListArray::new(item, offsets, values, nulls(lengths))
+}
+
+/// Validity of a nullable column, `None` if all the values are set
+fn nulls<T>(values: &[Option<T>]) -> Option<NullBuffer> {
+    match values.iter().all(Option::is_some) {
+        true => None,
+        false => Some(NullBuffer::from(
+            values.iter().map(Option::is_some).collect::<Vec<bool>>(),
+        )),
+    }
+}
+
+/// Values of a child of a struct column. The children of null items are null, which is allowed
+/// for the non-nullable children as the struct masks them
+fn field_values<'a, T, U>(
+    items: &[Option<&'a T>],
+    value: impl Fn(&'a T) -> Option<U>,
+) -> Vec<Option<U>> {
+    items.iter().map(|item| item.and_then(&value)).collect()
+}
+
+/// A list column with the items of each row as a struct built by `array`
+fn struct_list<'a, T: 'a>(
+    field: &Field,
+    rows: impl Iterator<Item = Option<&'a Vec<T>>>,
+    array: impl Fn(&[Option<&'a T>]) -> StructArray,
+) -> ListArray {
+    let mut lengths = vec![];
+    let mut items = vec![];
+    for row in rows {
+        lengths.push(row.map(Vec::len));
+        items.extend(row.into_iter().flatten().map(Some));
+    }
+    list_array(field, &lengths, Arc::new(array(&items)))
+}
+
+fn position_array(positions: &[Option<&TextPosition>]) -> StructArray {
+    let values = |value: fn(&TextPosition) -> Option<u64>| -> ArrayRef {
+        Arc::new(UInt64Array::from(field_values(positions, value)))
+    };
+    StructArray::new(
+        position_fields(),
+        vec![
+            values(|position| Some(position.utf16_offset)),
+            values(|position| Some(position.char_index)),
+            values(|position| Some(position.utf8_offset)),
+            values(|position| Some(position.line)),
+            values(|position| Some(position.column)),
+            values(|position| position.file_offset),
+        ],
+        nulls(positions),
+    )
+}
+
+fn cursor_text_array(texts: &[Option<&CursorText>]) -> StructArray {
+    StructArray::new(
+        cursor_text_fields(),
+        vec![
+            Arc::new(StringArray::from(field_values(texts, |text| {
+                text.saved.as_deref()
+            }))),
+            Arc::new(StringArray::from(field_values(texts, |text| {
+                text.reconstructed.as_deref()
+            }))),
+        ],
+        nulls(texts),
+    )
+}
+
+fn setting_array(settings: &[Option<&AppSettingData>]) -> StructArray {
+    StructArray::new(
+        setting_fields(),
+        vec![
+            Arc::new(BooleanArray::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::Bool(value) => Some(*value),
+                    _ => None,
+                },
+            ))),
+            Arc::new(Int64Array::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::Int(value) => Some(*value),
+                    _ => None,
+                },
+            ))),
+            Arc::new(UInt64Array::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::UInt(value) => Some(*value),
+                    _ => None,
+                },
+            ))),
+            Arc::new(Float64Array::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::Float(value) => Some(*value),
+                    _ => None,
+                },
+            ))),
+            Arc::new(StringArray::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::String(value) => Some(value.as_str()),
+                    _ => None,
+                },
+            ))),
+            Arc::new(StringArray::from(field_values(
+                settings,
+                |data| match data {
+                    AppSettingData::Raw(value) => Some(value.as_str()),
+                    _ => None,
+                },
+            ))),
+        ],
+        nulls(settings),
+    )
+}
+
+fn app_settings_array(settings: &[Option<&NotepadSettings>]) -> StructArray {
+    let setting = |value: fn(&NotepadSettings) -> Option<&AppSettingData>| -> ArrayRef {
+        Arc::new(setting_array(&field_values(settings, value)))
+    };
+    StructArray::new(
+        app_settings_fields(),
+        vec![
+            setting(|settings| settings.font_family.as_ref()),
+            setting(|settings| settings.font_size.as_ref()),
+            setting(|settings| settings.font_style.as_ref()),
+            setting(|settings| settings.theme.as_ref()),
+            setting(|settings| settings.open_file.as_ref()),
+            setting(|settings| settings.session_restore.as_ref()),
+            setting(|settings| settings.word_wrap.as_ref()),
+        ],
+        nulls(settings),
+    )
+}
+
+fn edit_session_array(sessions: &[Option<&EditSession>]) -> StructArray {
+    let index = |value: fn(&EditSession) -> u64| -> ArrayRef {
+        Arc::new(UInt64Array::from(field_values(sessions, |session| {
+            Some(value(session))
+        })))
+    };
+    StructArray::new(
+        edit_session_fields(),
+        vec![
+            Arc::new(StringArray::from(field_values(sessions, |session| {
+                enum_text(Some(&session.kind))
+            }))),
+            index(|session| session.first_chunk as u64),
+            index(|session| session.last_chunk as u64),
+            index(|session| session.position),
+            Arc::new(position_array(&field_values(sessions, |session| {
+                session.location.as_ref()
+            }))),
+            Arc::new(StringArray::from(field_values(sessions, |session| {
+                session.text.as_deref()
+            }))),
+            index(|session| session.deleted),
+            Arc::new(StringArray::from(field_values(sessions, |session| {
+                session.deleted_text.as_deref()
+            }))),
+        ],
+        nulls(sessions),
+    )
+}
+
+fn diff_line_array(lines: &[Option<&DiffLine>]) -> StructArray {
+    StructArray::new(
+        diff_line_fields(),
+        vec![
+            Arc::new(StringArray::from(field_values(lines, |line| {
+                enum_text(Some(&line.tag))
+            }))),
+            Arc::new(StringArray::from(field_values(lines, |line| {
+                Some(line.text.as_str())
+            }))),
+            Arc::new(BooleanArray::from(field_values(lines, |line| {
+                Some(line.no_newline_at_end)
+            }))),
+        ],
+        nulls(lines),
+    )
+}
+
+fn diff_hunk_array(hunks: &[Option<&DiffHunk>]) -> StructArray {
+    let fields = diff_hunk_fields();
+    let line = |value: fn(&DiffHunk) -> usize| -> ArrayRef {
+        Arc::new(UInt64Array::from(field_values(hunks, |hunk| {
+            Some(value(hunk) as u64)
+        })))
+    };
+    let lines = struct_list(
+        &fields[4],
+        hunks.iter().map(|hunk| hunk.map(|hunk| &hunk.lines)),
+        diff_line_array,
+    );
+    StructArray::new(
+        fields,
+        vec![
+            line(|hunk| hunk.old_start),
+            line(|hunk| hunk.old_lines),
+            line(|hunk| hunk.new_start),
+            line(|hunk| hunk.new_lines),
+            Arc::new(lines),
+        ],
+        nulls(hunks),
+    )
+}
+
+fn paste_array(pastes: &[Option<&PasteEvent>]) -> StructArray {
+    let number = |value: fn(&PasteEvent) -> u64| -> ArrayRef {
+        Arc::new(UInt64Array::from(field_values(pastes, |paste| {
+            Some(value(paste))
+        })))
+    };
+    StructArray::new(
+        paste_fields(),
+        vec![
+            number(|paste| paste.chunk as u64),
+            number(|paste| paste.position),
+            Arc::new(position_array(&field_values(pastes, |paste| {
+                paste.location.as_ref()
+            }))),
+            number(|paste| paste.length),
+            number(|paste| paste.replaced),
+            Arc::new(StringArray::from(field_values(pastes, |paste| {
+                Some(paste.text.as_str())
+            }))),
+        ],
+        nulls(pastes),
+    )
+}
+
+fn warning_array(warnings: &[Option<&LintWarning>]) -> StructArray {
+    StructArray::new(
+        warning_fields(),
+        vec![
+            Arc::new(StringArray::from(field_values(warnings, |warning| {
+                Some(warning.code.to_string())
+            }))),
+            Arc::new(StringArray::from(field_values(warnings, |warning| {
+                Some(warning.field.as_str())
+            }))),
+            Arc::new(StringArray::from(field_values(warnings, |warning| {
+                Some(warning.message.as_str())
+            }))),
+        ],
+        nulls(warnings),
+    )
+}
+
+/// Size of the text of `record` that is copied to the columns
+fn text_bytes(record: &NotepadTabStat) -> usize {
+    let chunks = record
+        .unsaved_chunks
+        .iter()
+        .flat_map(|chunks| chunks.iter())
+        .map(|chunk| chunk.data().map_or(0, str::len));
+    let sessions = record.edit_sessions.iter().flatten().map(|session| {
+        session.text.as_ref().map_or(0, String::len)
+            + session.deleted_text.as_ref().map_or(0, String::len)
+    });
+    let diff_lines = record
+        .diff_hunks
+        .iter()
+        .flatten()
+        .flat_map(|hunk| hunk.lines.iter())
+        .map(|line| line.text.len());
+    [
+        record.file_content.len(),
+        record.reconstructed_content().map_or(0, str::len),
+    ]
+    .into_iter()
+    .chain(chunks)
+    .chain(sessions)
+    .chain(diff_lines)
+    .fold(0, usize::saturating_add)
+}
+
+impl TabColumns {
+    fn push(&mut self, record: &NotepadTabStat) {
+        let times = record.tabstate_times.as_ref();
+        self.rows += 1;
+        self.tabstate_path.push(record.tabstate_path.clone());
+        self.tab_guid.push(record.tab_guid());
+        self.tabstate_modified
+            .push(timestamp(times.and_then(|times| times.modified.as_ref())));
+        self.tabstate_accessed
+            .push(timestamp(times.and_then(|times| times.accessed.as_ref())));
+        self.tabstate_changed
+            .push(timestamp(times.and_then(|times| times.changed.as_ref())));
+        self.tabstate_created
+            .push(timestamp(times.and_then(|times| times.created.as_ref())));
+        self.tabstate_size.push(record.tabstate_size);
+        self.notepad_version.push(record.notepad_version.clone());
+        self.seq_number.push(record.seq_number);
+        self.is_saved_file.push(record.is_saved_file);
+        self.path_size.push(record.path_size);
+        self.path.push(record.path.clone());
+        self.file_size.push(record.file_size);
+        self.encoding.push(enum_text(record.encoding.as_ref()));
+        self.cr_type.push(enum_text(record.cr_type.as_ref()));
+        self.last_write_time
+            .push(timestamp(record.last_write_time.as_ref()));
+        self.file_hash.push(record.file_hash.clone());
+        self.cursor_start.push(record.cursor_start);
+        self.cursor_end.push(record.cursor_end);
+        self.cursor_start_location
+            .push(record.cursor_start_location.clone());
+        self.cursor_end_location
+            .push(record.cursor_end_location.clone());
+        self.selected_text.push(record.selected_text.clone());
+        self.cursor_context.push(record.cursor_context.clone());
+        self.word_wrap.push(record.config_block.word_wrap);
+        self.rtl.push(record.config_block.rtl);
+        self.show_unicode.push(record.config_block.show_unicode);
+        self.version.push(record.config_block.version);
+        self.app_settings.push(record.app_settings.clone());
+        self.file_content_size.push(record.file_content_size);
+        self.file_content.push(record.file_content.clone());
+        self.contain_unsaved_data.push(record.contain_unsaved_data);
+        self.checksum.push(record.checksum.clone());
+
+        self.chunks
+            .push(record.unsaved_chunks.as_ref().map(|chunks| chunks.len()));
+        for chunk in record
+            .unsaved_chunks
+            .iter()
+            .flat_map(|chunks| chunks.iter())
+        {
+            self.chunk_position.push(chunk.position());
+            self.chunk_num_of_deletion.push(chunk.num_of_deletion());
+            self.chunk_num_of_addition.push(chunk.num_of_addition());
+            self.chunk_data
+                .push(chunk.data().map(|data| data.to_string()));
+            self.chunk_checksum.push(chunk.checksum().to_string());
+            self.chunk_checksum_valid.push(chunk.checksum_valid());
+            self.chunk_location.push(chunk.location().cloned());
+        }
+        self.reconstructed_content
+            .push(record.reconstructed_content().map(str::to_string));
+        self.bytes = self.bytes.saturating_add(text_bytes(record));
+        self.edit_sessions.push(record.edit_sessions.clone());
+        self.diff_hunks.push(record.diff_hunks.clone());
+        self.pastes.push(record.pastes.clone());
+        self.warnings.push(record.warnings.clone());
+
+        self.undecoded_fields
+            .push(Some(record.undecoded_fields.len()));
+        for field in &record.undecoded_fields {
+            self.undecoded_field.push(field.field.clone());
+            self.undecoded_offset.push(field.offset);
+            self.undecoded_value.push(field.value.clone());
+        }
+    }
+
+    /// Build a record batch from the buffered records and clear the buffer
+    fn take_batch(
+        &mut self,
+        schema: &SchemaRef,
+    ) -> std::result::Result<RecordBatch, NotepadErrors> {
+        let columns = mem::take(self);
+        let field = |name: &str| {
+            schema
+                .field_with_name(name)
+                .map_err(|e| columnar_error(e, "Invalid schema"))
+        };
+        let chunk_locations = columns
+            .chunk_location
+            .iter()
+            .map(Option::as_ref)
+            .collect::<Vec<Option<&TextPosition>>>();
+        let chunks = StructArray::new(
+            chunk_fields(),
+            vec![
+                Arc::new(UInt64Array::from(columns.chunk_position)),
+                Arc::new(UInt64Array::from(columns.chunk_num_of_deletion)),
+                Arc::new(UInt64Array::from(columns.chunk_num_of_addition)),
+                Arc::new(StringArray::from(columns.chunk_data)),
+                Arc::new(StringArray::from(columns.chunk_checksum)),
+                Arc::new(BooleanArray::from(columns.chunk_checksum_valid)),
+                Arc::new(position_array(&chunk_locations)),
+            ],
+            None,
+        );
+        let timestamps = |values: Vec<Option<i64>>| -> ArrayRef {
+            Arc::new(TimestampMicrosecondArray::from(values).with_timezone("UTC"))
+        };
+        let positions = |values: &[Option<TextPosition>]| -> ArrayRef {
+            let values = values.iter().map(Option::as_ref).collect::<Vec<_>>();
+            Arc::new(position_array(&values))
+        };
+        let cursor_texts = |values: &[Option<CursorText>]| -> ArrayRef {
+            let values = values.iter().map(Option::as_ref).collect::<Vec<_>>();
+            Arc::new(cursor_text_array(&values))
+        };
+        let app_settings = columns
+            .app_settings
+            .iter()
+            .map(Option::as_ref)
+            .collect::<Vec<Option<&NotepadSettings>>>();
+
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec![SCHEMA_VERSION; columns.rows])),
+            Arc::new(StringArray::from(columns.tabstate_path)),
+            Arc::new(StringArray::from(columns.tab_guid)),
+            timestamps(columns.tabstate_modified),
+            timestamps(columns.tabstate_accessed),
+            timestamps(columns.tabstate_changed),
+            timestamps(columns.tabstate_created),
+            Arc::new(UInt64Array::from(columns.tabstate_size)),
+            Arc::new(StringArray::from(columns.notepad_version)),
+            Arc::new(UInt64Array::from(columns.seq_number)),
+            Arc::new(BooleanArray::from(columns.is_saved_file)),
+            Arc::new(UInt64Array::from(columns.path_size)),
+            Arc::new(StringArray::from(columns.path)),
+            Arc::new(UInt64Array::from(columns.file_size)),
+            Arc::new(StringArray::from(columns.encoding)),
+            Arc::new(StringArray::from(columns.cr_type)),
+            timestamps(columns.last_write_time),
+            Arc::new(StringArray::from(columns.file_hash)),
+            Arc::new(UInt64Array::from(columns.cursor_start)),
+            Arc::new(UInt64Array::from(columns.cursor_end)),
+            positions(&columns.cursor_start_location),
+            positions(&columns.cursor_end_location),
+            cursor_texts(&columns.selected_text),
+            cursor_texts(&columns.cursor_context),
+            Arc::new(BooleanArray::from(columns.word_wrap)),
+            Arc::new(BooleanArray::from(columns.rtl)),
+            Arc::new(BooleanArray::from(columns.show_unicode)),
+            Arc::new(UInt64Array::from(columns.version)),
+            Arc::new(app_settings_array(&app_settings)),
+            Arc::new(UInt64Array::from(columns.file_content_size)),
+            Arc::new(StringArray::from(columns.file_content)),
+            Arc::new(BooleanArray::from(columns.contain_unsaved_data)),
+            Arc::new(StringArray::from(columns.checksum)),
+            Arc::new(list_array(
+                field("unsaved_chunks")?,
+                &columns.chunks,
+                Arc::new(chunks),
+            )),
+            Arc::new(StringArray::from(columns.reconstructed_content)),
+            Arc::new(struct_list(
+                field("edit_sessions")?,
+                columns.edit_sessions.iter().map(Option::as_ref),
+                edit_session_array,
+            )),
+            Arc::new(struct_list(
+                field("diff_hunks")?,
+                columns.diff_hunks.iter().map(Option::as_ref),
+                diff_hunk_array,
+            )),
+            Arc::new(struct_list(
+                field("pastes")?,
+                columns.pastes.iter().map(Option::as_ref),
+                paste_array,
+            )),
+            Arc::new(struct_list(
+                field("warnings")?,
+                columns.warnings.iter().map(Option::as_ref),
+                warning_array,
+            )),
+        ];
+        if let Ok(field) = schema.field_with_name("undecoded_fields") {
+            let undecoded_fields = StructArray::new(
+                undecoded_field_fields(),
+                vec![
+                    Arc::new(StringArray::from(columns.undecoded_field)),
+                    Arc::new(UInt64Array::from(columns.undecoded_offset)),
+                    Arc::new(StringArray::from(columns.undecoded_value)),
+                ],
+                None,
+            );
+            arrays.push(Arc::new(list_array(
+                field,
+                &columns.undecoded_fields,
+                Arc::new(undecoded_fields),
+            )));
+        }
+
+        RecordBatch::try_new(schema.clone(), arrays)
+            .map_err(|e| columnar_error(e, "Unable to build the record batch"))
+    }
+}
+
+/// Writes the record batches of a columnar sink
+trait BatchWriter {
+    fn write_batch(&mut self, batch: &RecordBatch) -> std::result::Result<(), NotepadErrors>;
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors>;
+}
+
+/// Buffers `BATCH_SIZE` records or `BATCH_BYTES` of text and writes them with a `BatchWriter`.
+/// Summaries don't fit the records schema and can't be written
+struct ColumnarSink<W: BatchWriter> {
+    writer: W,
+    schema: SchemaRef,
+    columns: TabColumns,
+}
+
+impl<W: BatchWriter> ColumnarSink<W> {
+    fn flush_batch(&mut self) -> std::result::Result<(), NotepadErrors> {
+        if self.columns.rows == 0 {
+            return Ok(());
+        }
+        let batch = self.columns.take_batch(&self.schema)?;
+        self.writer.write_batch(&batch)
+    }
+}
+
+impl<W: BatchWriter> OutputSink for ColumnarSink<W> {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        self.columns.push(record);
+        match self.columns.rows >= BATCH_SIZE || self.columns.bytes >= BATCH_BYTES {
+            true => self.flush_batch(),
+            false => Ok(()),
+        }
+    }
+
+    fn summary(&mut self, _summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        Err(columnar_error(
+            "Summaries aren't supported by the columnar output",
+            "Use the jsonl, csv or sqlite output for summaries",
+        ))
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.flush_batch()?;
+        self.writer.finish()
+    }
+}
+
+/// Parquet needs a `Send` writer, the row groups are written to a buffer that is moved to the
+/// output after every batch
+struct ParquetWriter {
+    writer: Option<ArrowWriter<Vec<u8>>>,
+    output: Box<dyn Write>,
+}
+
+impl ParquetWriter {
+    fn drain(&mut self) -> std::result::Result<(), NotepadErrors> {
+        let buffer = match self.writer.as_mut() {
+            Some(writer) => mem::take(writer.inner_mut()),
+            None => return Ok(()),
+        };
+        self.output
+            .write_all(&buffer)
+            .map_err(|e| columnar_error(e, "Unable to write the Parquet data"))
+    }
+}
+
+impl BatchWriter for ParquetWriter {
+    fn write_batch(&mut self, batch: &RecordBatch) -> std::result::Result<(), NotepadErrors> {
+        match self.writer.as_mut() {
+            Some(writer) => writer
+                .write(batch)
+                .and_then(|_| writer.flush())
+                .map_err(|e| columnar_error(e, "Unable to write the Parquet row group"))?,
+            None => {
+                return Err(columnar_error(
+                    "The writer is closed",
+                    "Unable to write the Parquet row group",
+                ))
+            }
+        }
+        self.drain()
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer
+                .finish()
+                .map_err(|e| columnar_error(e, "Unable to write the Parquet footer"))?;
+        }
+        self.drain()?;
+        self.writer = None;
+        self.output
+            .flush()
+            .map_err(|e| columnar_error(e, "Unable to flush the output"))
+    }
+}
+
+/// Records as a Parquet file, with one row group per batch of records
+pub struct ParquetSink(ColumnarSink<ParquetWriter>);
+
+impl ParquetSink {
+    pub fn new(
+        output: Box<dyn Write>,
+        options: &SinkOptions,
+    ) -> std::result::Result<Self, NotepadErrors> {
+        let schema = Arc::new(tab_schema(options.research));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(BATCH_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(vec![], schema.clone(), Some(properties))
+            .map_err(|e| columnar_error(e, "Unable to create the Parquet writer"))?;
+        Ok(Self(ColumnarSink {
+            writer: ParquetWriter {
+                writer: Some(writer),
+                output,
+            },
+            schema,
+            columns: TabColumns::default(),
+        }))
+    }
+}
+
+impl OutputSink for ParquetSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        self.0.record(record)
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        self.0.summary(summary)
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.0.finish()
+    }
+}
+
+impl BatchWriter for StreamWriter<Box<dyn Write>> {
+    fn write_batch(&mut self, batch: &RecordBatch) -> std::result::Result<(), NotepadErrors> {
+        self.write(batch)
+            .map_err(|e| columnar_error(e, "Unable to write the Arrow record batch"))
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        StreamWriter::finish(self)
+            .and_then(|_| self.get_mut().flush().map_err(Into::into))
+            .map_err(|e| columnar_error(e, "Unable to finish the Arrow stream"))
+    }
+}
+
+/// Records as an Arrow IPC stream with one record batch per batch of records
+pub struct ArrowSink(ColumnarSink<StreamWriter<Box<dyn Write>>>);
+
+impl ArrowSink {
+    pub fn new(
+        output: Box<dyn Write>,
+        options: &SinkOptions,
+    ) -> std::result::Result<Self, NotepadErrors> {
+        let schema = Arc::new(tab_schema(options.research));
+        let writer = StreamWriter::try_new(output, &schema)
+            .map_err(|e| columnar_error(e, "Unable to create the Arrow writer"))?;
+        Ok(Self(ColumnarSink {
+            writer,
+            schema,
+            columns: TabColumns::default(),
+        }))
+    }
+}
+
+impl OutputSink for ArrowSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        self.0.record(record)
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        self.0.summary(summary)
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.0.finish()
+    }
+}
@@ -0,0 +1,115 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Number of unchanged lines included around the changes of each hunk
+pub const DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTag {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl From<ChangeTag> for DiffTag {
+    fn from(value: ChangeTag) -> Self {
+        match value {
+            ChangeTag::Equal => DiffTag::Equal,
+            ChangeTag::Delete => DiffTag::Delete,
+            ChangeTag::Insert => DiffTag::Insert,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    /// Text of the line without its line break
+    pub text: String,
+    /// The line is the last one and doesn't end with a line break
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_newline_at_end: bool,
+}
+
+/// A group of changed lines with their context. Line numbers start from 1
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Notepad uses `\r` as line break in the buffer, normalize all line breaks to `\n`
+fn normalize_line_breaks(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Compare `old` and `new` line by line and return the changed hunks
+pub fn diff_hunks(old: &str, new: &str) -> Vec<DiffHunk> {
+    let (old, new) = (normalize_line_breaks(old), normalize_line_breaks(new));
+    let diff = TextDiff::from_lines(&old, &new);
+
+    diff.grouped_ops(DIFF_CONTEXT_LINES)
+        .iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    tag: DiffTag::from(change.tag()),
+                    text: change.value().trim_end_matches('\n').to_string(),
+                    no_newline_at_end: change.missing_newline(),
+                })
+                .collect();
+
+            Some(DiffHunk {
+                old_start: old_range.start + 1,
+                old_lines: old_range.len(),
+                new_start: new_range.start + 1,
+                new_lines: new_range.len(),
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Unified diff between `old` and `new`
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let (old, new) = (normalize_line_breaks(old), normalize_line_breaks(new));
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT_LINES)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+/// Word level diff between `old` and `new`. Deleted words are wrapped in `[-` `-]` and added words in `{+` `+}`
+pub fn word_diff(old: &str, new: &str) -> String {
+    let (old, new) = (normalize_line_breaks(old), normalize_line_breaks(new));
+    let diff = TextDiff::from_words(&old, &new);
+
+    // Merge consecutive changes with the same tag
+    let mut groups: Vec<(ChangeTag, String)> = vec![];
+    for change in diff.iter_all_changes() {
+        match groups.last_mut() {
+            Some((tag, text)) if *tag == change.tag() => text.push_str(change.value()),
+            _ => groups.push((change.tag(), change.value().to_string())),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(tag, text)| match tag {
+            ChangeTag::Equal => text,
+            ChangeTag::Delete => format!("[-{}-]", text),
+            ChangeTag::Insert => format!("{{+{}+}}", text),
+        })
+        .collect()
+}
@@ -1,6 +1,7 @@
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[repr(u8)]
 pub enum Encoding {
     ANSI = 0x01,
@@ -24,7 +25,7 @@ impl From<u8> for Encoding {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[repr(u8)]
 pub enum CRType {
     CRLF = 0x1,
@@ -22,4 +22,6 @@ pub enum NotepadErrors {
     FileOpen(String, String),
     #[error("CLI error. ERROR: '{0}', MSG: '{1}'")]
     CLIError(String, String),
+    #[error("Unable to write the output. ERROR: '{0}', MSG: '{1}'")]
+    Output(String, String),
 }
@@ -0,0 +1,336 @@
+use crate::{
+    analysis::EditKind,
+    errors::NotepadErrors,
+    output::{enum_text, OutputSink},
+    schema::SCHEMA_VERSION,
+    summary::TabStateSummary,
+    timestamps::{datetime_text, filetime_text},
+    NotepadTabStat,
+};
+use std::{fmt::Display, io::Write};
+
+/// Start of the report, the styles are inlined so the report works offline
+const HEADER: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Notepad TabState report</title>
+<style>
+body { font-family: "Segoe UI", Arial, sans-serif; background: #f3f4f6; color: #111827; margin: 0; padding: 24px; }
+header, section { max-width: 1100px; margin: 0 auto 24px; }
+section { background: #fff; border: 1px solid #d1d5db; border-radius: 8px; padding: 16px 20px; }
+section.error { border-color: #dc2626; }
+h1 { font-size: 1.5em; margin: 0 0 4px; }
+h2 { font-size: 1.15em; margin: 0 0 12px; word-break: break-all; }
+h3 { font-size: 1em; margin: 16px 0 8px; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9em; }
+th, td { border: 1px solid #e5e7eb; padding: 4px 8px; text-align: left; vertical-align: top; word-break: break-all; }
+table.meta th { width: 200px; background: #f9fafb; }
+pre { background: #f9fafb; border: 1px solid #e5e7eb; padding: 8px; white-space: pre-wrap; word-break: break-word; margin: 0; }
+.change { white-space: pre-wrap; font-family: Consolas, monospace; }
+ins { background: #dcfce7; color: #166534; text-decoration: none; }
+del { background: #fee2e2; color: #991b1b; }
+.invalid { color: #dc2626; font-weight: bold; }
+.warning { color: #b45309; }
+.muted { color: #6b7280; }
+</style>
+</head>
+<body>
+"#;
+
+const FOOTER: &str = "</body>\n</html>\n";
+
+/// Escape `text` to be used in an element or an attribute
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape `text` and show line breaks in the chunks, a chunk adding a new line is otherwise empty
+fn escape_chunk(text: &str) -> String {
+    escape_html(text)
+        .replace("\r\n", "\u{21B5}\n")
+        .replace('\r', "\u{21B5}\n")
+}
+
+fn html_error(e: std::io::Error, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// Rows of a metadata table, rows without a value are left out
+#[derive(Default)]
+struct Metadata(String);
+
+impl Metadata {
+    fn row<T: Display>(&mut self, name: &str, value: Option<T>) -> &mut Self {
+        if let Some(value) = value {
+            self.0.push_str(&format!(
+                "<tr><th>{}</th><td>{}</td></tr>\n",
+                name,
+                escape_html(&value.to_string())
+            ));
+        }
+        self
+    }
+
+    fn table(&self) -> String {
+        format!("<table class=\"meta\">\n{}</table>\n", self.0)
+    }
+}
+
+/// Each edit session as a row with the deleted text in red and the inserted text in green. The
+/// deleted text is the one recovered by `analysis::edit_sessions`. The chunks of the edit with an
+/// invalid checksum are listed with it
+fn edits_table(record: &NotepadTabStat) -> Option<String> {
+    let chunks = record.unsaved_chunks.as_ref().filter(|c| !c.is_empty())?;
+    let sessions = record.edit_sessions.as_ref().filter(|s| !s.is_empty())?;
+    let mut rows = String::new();
+    for (index, session) in sessions.iter().enumerate() {
+        let deleted = match (&session.kind, session.deleted_text.as_deref()) {
+            (EditKind::Typed, _) => String::new(),
+            (_, Some(text)) => format!("<del>{}</del>", escape_chunk(text)),
+            (_, None) => format!("<del>{} characters</del>", session.deleted),
+        };
+        let inserted = match session.text.as_deref() {
+            Some(text) => format!("<ins>{}</ins>", escape_chunk(text)),
+            None => String::new(),
+        };
+        let invalid_checksums = chunks
+            .iter()
+            .enumerate()
+            .skip(session.first_chunk)
+            .take(session.last_chunk.saturating_sub(session.first_chunk) + 1)
+            .filter(|(_, chunk)| chunk.checksum_valid() == Some(false))
+            .map(|(index, chunk)| {
+                format!(
+                    "<span class=\"invalid\">{} (chunk {})</span>",
+                    chunk.checksum(),
+                    index
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("<br>");
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}-{}</td><td>{}</td><td>{}</td><td class=\"change\" dir=\"{}\">{}{}</td><td>{}</td></tr>\n",
+            index,
+            session.first_chunk,
+            session.last_chunk,
+            session.position,
+            enum_text(Some(&session.kind)).unwrap_or_default(),
+            direction(record),
+            deleted,
+            inserted,
+            invalid_checksums
+        ));
+    }
+    Some(format!(
+        "<table>\n<tr><th>#</th><th>Chunks</th><th>Position</th><th>Edit</th><th>Change</th><th>Invalid checksums</th></tr>\n{}</table>\n",
+        rows
+    ))
+}
+
+/// Text direction of the content, from the right-to-left setting of the tab
+fn direction(record: &NotepadTabStat) -> &'static str {
+    match record.config_block.rtl {
+        true => "rtl",
+        false => "ltr",
+    }
+}
+
+fn content_block(title: &str, content: &str, dir: &str) -> String {
+    format!(
+        "<h3>{}</h3>\n<pre dir=\"{}\">{}</pre>\n",
+        title,
+        dir,
+        escape_html(content)
+    )
+}
+
+/// A single offline HTML file with a card for each tab showing its metadata, the saved content,
+/// the reconstructed buffer and the unsaved chunks. Summaries and files that failed to parse get
+/// their own cards
+pub struct HtmlSink {
+    output: Box<dyn Write>,
+}
+
+impl HtmlSink {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self { output }
+    }
+
+    fn write(&mut self, html: &str) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .write_all(html.as_bytes())
+            .map_err(|e| html_error(e, "Unable to write the report"))
+    }
+}
+
+impl OutputSink for HtmlSink {
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        let header = format!(
+            "{}<header>\n<h1>Notepad TabState report</h1>\n<div class=\"muted\">notepad_parser v{}, schema version {}</div>\n</header>\n",
+            HEADER,
+            env!("CARGO_PKG_VERSION"),
+            SCHEMA_VERSION
+        );
+        self.write(&header)
+    }
+
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        let title = match (&record.path, record.tab_guid()) {
+            (Some(path), _) => path.clone(),
+            (None, Some(guid)) => format!("Unsaved tab {}", guid),
+            (None, None) => "Unsaved tab".to_string(),
+        };
+        let times = record.tabstate_times.as_ref();
+        let config = &record.config_block;
+        let chunks = record.unsaved_chunks.as_ref().map_or(0, |c| c.len());
+        let mut metadata = Metadata::default();
+        metadata
+            .row("TabState path", record.tabstate_path.as_ref())
+            .row("Tab GUID", record.tab_guid())
+            .row("Saved file", Some(record.is_saved_file))
+            .row("Path", record.path.as_ref())
+            .row("File size", record.file_size)
+            .row("Encoding", enum_text(record.encoding.as_ref()))
+            .row("Line endings", enum_text(record.cr_type.as_ref()))
+            .row(
+                "Last write time",
+                record.last_write_time.as_ref().map(filetime_text),
+            )
+            .row("File hash (SHA256)", record.file_hash.as_ref())
+            .row("Checksum (CRC32)", Some(&record.checksum))
+            .row(
+                "TabState modified",
+                times.and_then(|t| t.modified.as_ref()).map(filetime_text),
+            )
+            .row(
+                "TabState accessed",
+                times.and_then(|t| t.accessed.as_ref()).map(filetime_text),
+            )
+            .row(
+                "TabState changed",
+                times.and_then(|t| t.changed.as_ref()).map(filetime_text),
+            )
+            .row(
+                "TabState created",
+                times.and_then(|t| t.created.as_ref()).map(filetime_text),
+            )
+            .row("Sequence number", Some(record.seq_number))
+            .row("Cursor start", record.cursor_start)
+            .row("Cursor end", record.cursor_end)
+            .row("Word wrap", Some(config.word_wrap))
+            .row("Right-to-left", Some(config.rtl))
+            .row("Show unicode", Some(config.show_unicode))
+            .row("Config version", Some(config.version))
+            .row("Notepad version", record.notepad_version.as_ref())
+            .row("Unsaved chunks", Some(chunks));
+
+        let dir = direction(record);
+        let mut card = format!(
+            "<section class=\"tab\">\n<h2>{}</h2>\n{}",
+            escape_html(&title),
+            metadata.table()
+        );
+        if let Some(warnings) = &record.warnings {
+            card.push_str("<h3>Warnings</h3>\n<ul>\n");
+            for warning in warnings {
+                card.push_str(&format!(
+                    "<li class=\"warning\">{}</li>\n",
+                    escape_html(&warning.to_string())
+                ));
+            }
+            card.push_str("</ul>\n");
+        }
+        let content_title = match record.is_saved_file {
+            true => "Saved content",
+            false => "Content",
+        };
+        card.push_str(&content_block(content_title, &record.file_content, dir));
+        if let Some(content) = record.reconstructed_content() {
+            card.push_str(&content_block("Reconstructed buffer", content, dir));
+        }
+        if let Some(table) = edits_table(record) {
+            card.push_str("<h3>Unsaved edits</h3>\n");
+            card.push_str(&table);
+        }
+        card.push_str("</section>\n");
+        self.write(&card)
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        let mut metadata = Metadata::default();
+        metadata
+            .row("Tabs", Some(summary.tabs_count))
+            .row("Saved tabs", Some(summary.saved_tabs))
+            .row("Unsaved tabs", Some(summary.unsaved_tabs))
+            .row(
+                "Last activity",
+                summary.last_activity.as_ref().map(datetime_text),
+            );
+        let mut card = format!(
+            "<section class=\"summary\">\n<h2>TabState directory {}</h2>\n{}",
+            escape_html(&summary.tabstate_dir),
+            metadata.table()
+        );
+        card.push_str("<h3>Tabs</h3>\n<table>\n<tr><th>Tab GUID</th><th>Path</th><th>Saved file</th><th>Unsaved chunks</th><th>Last activity</th></tr>\n");
+        for tab in &summary.tabs {
+            card.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&tab.tab_guid),
+                escape_html(tab.path.as_deref().unwrap_or_default()),
+                tab.is_saved_file,
+                tab.has_unsaved_chunks,
+                tab.last_activity
+                    .as_ref()
+                    .map(datetime_text)
+                    .unwrap_or_default()
+            ));
+        }
+        card.push_str("</table>\n");
+        if !summary.errors.is_empty() {
+            card.push_str("<h3>Errors</h3>\n<ul>\n");
+            for error in &summary.errors {
+                card.push_str(&format!(
+                    "<li class=\"invalid\">{}</li>\n",
+                    escape_html(error)
+                ));
+            }
+            card.push_str("</ul>\n");
+        }
+        card.push_str("</section>\n");
+        self.write(&card)
+    }
+
+    /// Files that failed to parse get a card with the error
+    fn error(
+        &mut self,
+        path: &str,
+        error: &NotepadErrors,
+    ) -> std::result::Result<(), NotepadErrors> {
+        let card = format!(
+            "<section class=\"error\">\n<h2>{}</h2>\n<pre class=\"invalid\">{}</pre>\n</section>\n",
+            escape_html(path),
+            escape_html(&error.to_string())
+        );
+        self.write(&card)
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.write(FOOTER)?;
+        self.output
+            .flush()
+            .map_err(|e| html_error(e, "Unable to flush the output"))
+    }
+}
@@ -1,17 +1,47 @@
 /// A Library to parse Windows Notepad `TabState` artifacts
+pub mod analysis;
+#[cfg(feature = "parquet")]
+pub mod columnar;
+pub mod diff;
 pub mod enums;
 pub mod errors;
+pub mod html;
+pub mod lint;
+pub mod markdown;
+pub mod output;
+pub mod package;
+pub mod position;
+pub mod research;
+pub mod schema;
+pub mod settings;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod summary;
 #[cfg(test)]
 mod tests;
+pub mod timeline;
+pub mod timestamps;
 pub mod traits;
 pub mod unsaved_chunks;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
 
+use analysis::{EditSession, PasteEvent, DEFAULT_PASTE_MIN_LENGTH};
 use byteorder::ReadBytesExt;
+use diff::DiffHunk;
 use enums::{CRType, Encoding};
 use errors::NotepadErrors;
-use serde::Serialize;
+use lint::LintWarning;
+use position::{map_position, text_range, CursorText, TextPosition, DEFAULT_CONTEXT_SIZE};
+use research::{PositionReader, RecordingReader, UndecodedField};
+use schema::SchemaVersion;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::NotepadSettings;
 use std::convert::From;
 use std::io::Read;
+use std::path::Path;
+use timestamps::FileSystemTimes;
 use unsaved_chunks::UnsavedChunks;
 use winparsingtools::{
     date_time::FileTime, utils::bytes_to_hex, utils::read_uleb128, utils::read_utf16_string,
@@ -20,14 +50,14 @@ use winparsingtools::{
 use std::fs::File;
 use traits::ReadBool;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 pub struct ConfigBlock {
     pub word_wrap: bool,
     pub rtl: bool,
     pub show_unicode: bool,
     pub version: u64,
-    unknown0: u8,
-    unknown1: u8,
+    pub unknown0: u8,
+    pub unknown1: u8,
 }
 
 impl ConfigBlock {
@@ -102,25 +132,26 @@ impl ConfigBlock {
     }
 }
 
-impl Default for ConfigBlock {
-    fn default() -> Self {
-        Self {
-            word_wrap: false,
-            rtl: false,
-            show_unicode: false,
-            version: 0,
-            unknown0: 0,
-            unknown1: 0,
-        }
-    }
-}
 /// Represents the structure for `TabState` files
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[allow(dead_code)]
 pub struct NotepadTabStat {
+    /// Version of the output schema, see `schema::SCHEMA_VERSION`
+    #[serde(default)]
+    pub schema_version: SchemaVersion,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tabstate_path: Option<String>,
-    #[serde(skip_serializing)]
+    /// Filesystem timestamps of the `TabState` file, available when parsed with `from_path`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tabstate_times: Option<FileSystemTimes>,
+    /// Size of the `TabState` file in bytes, available when parsed with `from_path`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tabstate_size: Option<u64>,
+    /// Version of the installed Notepad package, see `package::NotepadPackage::detect`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notepad_version: Option<String>,
+    #[serde(skip_serializing, default)]
+    #[schemars(skip)]
     pub signature: [u8; 2],
     // #[serde(skip_serializing)]
     pub seq_number: u64,
@@ -134,31 +165,77 @@ pub struct NotepadTabStat {
     pub encoding: Option<Encoding>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cr_type: Option<CRType>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "timestamps::serialize_filetime",
+        deserialize_with = "timestamps::deserialize_filetime"
+    )]
+    #[schemars(schema_with = "schema::filetime_schema")]
     pub last_write_time: Option<FileTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file_hash: Option<String>,
     #[serde(skip_serializing)]
+    #[schemars(skip)]
     pub unknown1: Option<[u8; 2]>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cursor_start: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cursor_end: Option<u64>,
+    /// `cursor_start` mapped to characters and line/column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_start_location: Option<TextPosition>,
+    /// `cursor_end` mapped to characters and line/column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_end_location: Option<TextPosition>,
+    /// Text between `cursor_start` and `cursor_end` when they differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_text: Option<CursorText>,
+    /// Characters on both sides of the cursor, see `update_cursor_text`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_context: Option<CursorText>,
     pub config_block: ConfigBlock,
+    /// Application wide settings from the Notepad package `settings.dat`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_settings: Option<NotepadSettings>,
     pub file_content_size: u64,
     pub file_content: String,
     pub contain_unsaved_data: bool,
     pub checksum: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unsaved_chunks: Option<UnsavedChunks>,
+    /// The buffer after replaying the unsaved chunks, see `reconstructed_content`. Not serialized,
+    /// set by `update_reconstructed_content`
+    #[serde(skip)]
+    #[schemars(skip)]
+    reconstructed_content: Option<String>,
+    /// Unsaved chunks merged into higher level edits, see `analysis::edit_sessions`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edit_sessions: Option<Vec<EditSession>>,
+    /// Line changes between `file_content` and the buffer after replaying the unsaved chunks.
+    /// Only for tabs of saved files with unsaved chunks
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub unsaved_chunks_str: Option<String>,
+    pub diff_hunks: Option<Vec<DiffHunk>>,
+    /// Unsaved chunks adding multiple characters at once, see `analysis::classify_input`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pastes: Option<Vec<PasteEvent>>,
+    /// Suspicious values found while parsing, see `lint::lint`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<LintWarning>>,
+    /// Fields with unknown meaning and their offsets. Serialized only through `research::ResearchRecord`
+    #[serde(skip_serializing, default)]
+    #[schemars(skip)]
+    pub undecoded_fields: Vec<UndecodedField>,
 }
 
 impl Default for NotepadTabStat {
     fn default() -> Self {
         Self {
+            schema_version: SchemaVersion,
             tabstate_path: Option::None,
+            tabstate_times: Option::None,
+            tabstate_size: Option::None,
+            notepad_version: Option::None,
             signature: [0x4E, 0x50],
             seq_number: 0x00,
             is_saved_file: false,
@@ -172,13 +249,23 @@ impl Default for NotepadTabStat {
             unknown1: Option::None,
             cursor_start: Option::None,
             cursor_end: Option::None,
+            cursor_start_location: Option::None,
+            cursor_end_location: Option::None,
+            selected_text: Option::None,
+            cursor_context: Option::None,
             config_block: ConfigBlock::default(),
+            app_settings: Option::None,
             file_content_size: 0,
             file_content: String::from("Hello :D"),
             contain_unsaved_data: false,
             checksum: String::from("41414141"),
             unsaved_chunks: Option::None,
-            unsaved_chunks_str: Option::None,
+            reconstructed_content: Option::None,
+            edit_sessions: Option::None,
+            diff_hunks: Option::None,
+            pastes: Option::None,
+            warnings: Option::None,
+            undecoded_fields: vec![],
         }
     }
 }
@@ -188,7 +275,7 @@ impl NotepadTabStat {
     pub fn from_path(path: &str) -> std::result::Result<Self, NotepadErrors> {
         let mut file = match File::open(path) {
             Ok(file) => file,
-            Err(e) => return Err(NotepadErrors::FileOpen(e.to_string(), format!("{}", path))),
+            Err(e) => return Err(NotepadErrors::FileOpen(e.to_string(), path.to_string())),
         };
 
         let mut parsed = match NotepadTabStat::from_reader(&mut file) {
@@ -203,12 +290,155 @@ impl NotepadTabStat {
         };
 
         parsed.tabstate_path = Some(String::from(path));
+        if let Ok(metadata) = file.metadata() {
+            parsed.tabstate_times = Some(FileSystemTimes::from(&metadata));
+            parsed.tabstate_size = Some(metadata.len());
+        }
+        parsed.update_warnings();
 
         Ok(parsed)
     }
 
     /// Parse data from reader
     pub fn from_reader<R: Read>(reader: &mut R) -> std::result::Result<Self, NotepadErrors> {
+        let mut parsed = Self::parse_fields(reader)?;
+
+        parsed.cursor_start_location = parsed.cursor_start.and_then(|pos| parsed.map_position(pos));
+        parsed.cursor_end_location = parsed.cursor_end.and_then(|pos| parsed.map_position(pos));
+        // Replay the chunks once for their locations, the deleted text and the reconstructed buffer
+        if let Some(unsaved_chunks) = parsed.unsaved_chunks.as_mut() {
+            let replay = unsaved_chunks.replay_mapped(
+                &parsed.file_content,
+                parsed.encoding.as_ref(),
+                parsed.cr_type.as_ref(),
+            );
+            unsaved_chunks.set_locations(replay.locations);
+            parsed.edit_sessions = Some(analysis::replayed_edit_sessions(
+                unsaved_chunks,
+                &replay.deleted_texts,
+            ));
+            parsed.reconstructed_content = Some(replay.content);
+        }
+        parsed.diff_hunks = parsed
+            .saved_file_reconstructed_content()
+            .map(|content| diff::diff_hunks(&parsed.file_content, content));
+        parsed.update_pastes(DEFAULT_PASTE_MIN_LENGTH);
+        parsed.update_cursor_text(DEFAULT_CONTEXT_SIZE);
+        parsed.update_warnings();
+
+        Ok(parsed)
+    }
+
+    /// Name of the `TabState` file without the extension, which is the tab GUID
+    pub fn tab_guid(&self) -> Option<String> {
+        self.tabstate_path
+            .as_ref()
+            .and_then(|path| Path::new(path).file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+    }
+
+    /// Run the `lint` checks and set `warnings`
+    fn update_warnings(&mut self) {
+        self.warnings = Some(lint::lint(self)).filter(|warnings| !warnings.is_empty());
+    }
+
+    /// The buffer after replaying the unsaved chunks on top of `file_content`.
+    /// Returns `None` if there are no unsaved chunks
+    pub fn reconstructed_content(&self) -> Option<&str> {
+        self.reconstructed_content.as_deref()
+    }
+
+    /// Replay the unsaved chunks and store the result for `reconstructed_content`. Done by
+    /// `from_reader`, call it after deserializing a record or changing its unsaved chunks
+    pub fn update_reconstructed_content(&mut self) {
+        self.reconstructed_content = self
+            .unsaved_chunks
+            .as_ref()
+            .map(|chunks| chunks.replay(&self.file_content));
+    }
+
+    /// Unified diff between `file_content` and the reconstructed buffer.
+    /// Returns `None` if the tab isn't for a saved file or there are no unsaved chunks
+    pub fn unified_diff(&self) -> Option<String> {
+        let path = self.path.as_deref().unwrap_or_default();
+        self.saved_file_reconstructed_content().map(|content| {
+            diff::unified_diff(
+                &self.file_content,
+                content,
+                &format!("{} (saved)", path),
+                &format!("{} (unsaved buffer)", path),
+            )
+        })
+    }
+
+    /// Word level diff between `file_content` and the reconstructed buffer.
+    /// Returns `None` if the tab isn't for a saved file or there are no unsaved chunks
+    pub fn word_diff(&self) -> Option<String> {
+        self.saved_file_reconstructed_content()
+            .map(|content| diff::word_diff(&self.file_content, content))
+    }
+
+    fn saved_file_reconstructed_content(&self) -> Option<&str> {
+        self.reconstructed_content().filter(|_| self.is_saved_file)
+    }
+
+    /// Compute `pastes` from the unsaved chunks adding at least `paste_min_length` characters
+    pub fn update_pastes(&mut self, paste_min_length: u64) {
+        self.pastes = self
+            .unsaved_chunks
+            .as_ref()
+            .map(|chunks| analysis::pastes(chunks, paste_min_length))
+            .filter(|pastes| !pastes.is_empty());
+    }
+
+    /// Compute `selected_text` and `cursor_context` with `context_size` characters on each side of the cursor
+    pub fn update_cursor_text(&mut self, context_size: usize) {
+        let (start, end) = match (self.cursor_start, self.cursor_end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return,
+        };
+        let reconstructed = self.reconstructed_content();
+        let contents = [Some(self.file_content.as_str()), reconstructed];
+        let [saved, reconstructed] = contents.map(|content| {
+            content
+                .and_then(|c| text_range(c, start, end, context_size))
+                .filter(|text| !text.is_empty())
+        });
+        let [saved_selection, reconstructed_selection] = contents.map(|content| {
+            content
+                .filter(|_| start != end)
+                .and_then(|c| text_range(c, start, end, 0))
+        });
+
+        let cursor_context = CursorText {
+            saved,
+            reconstructed,
+        };
+        let selected_text = CursorText {
+            saved: saved_selection,
+            reconstructed: reconstructed_selection,
+        };
+        self.cursor_context = Some(cursor_context).filter(|text| !text.is_empty());
+        self.selected_text = Some(selected_text).filter(|text| !text.is_empty());
+    }
+
+    /// Map `utf16_offset` in `file_content` to characters, UTF-8 bytes and line/column.
+    /// Returns `None` if the offset is outside `file_content`
+    pub fn map_position(&self, utf16_offset: u64) -> Option<TextPosition> {
+        map_position(
+            &self.file_content,
+            utf16_offset,
+            self.encoding.as_ref(),
+            self.cr_type.as_ref(),
+        )
+    }
+
+    /// Parse the fields of the `TabState` file from reader
+    fn parse_fields<R: Read>(reader: &mut R) -> std::result::Result<Self, NotepadErrors> {
+        // Keep track of the offsets to report them for the undecoded fields
+        let reader = &mut PositionReader::new(reader);
+        let mut undecoded_fields = vec![];
+
         // Read first two bytes as `signature`
         let mut signature = [0u8; 2];
         if let Err(e) = reader.read_exact(&mut signature) {
@@ -255,8 +485,10 @@ impl NotepadTabStat {
             }
         };
 
-        // Read `path_size`
-        let path_size = match read_uleb128(reader) {
+        // Read `path_size`. Keep its bytes to report them for unsaved tabs that have no path
+        let path_size_offset = reader.position();
+        let mut path_size_reader = RecordingReader::new(reader);
+        let path_size = match read_uleb128(&mut path_size_reader) {
             Ok(size) => size,
             Err(e) => {
                 return Err(NotepadErrors::ReadError(
@@ -265,6 +497,7 @@ impl NotepadTabStat {
                 ))
             }
         };
+        let path_size_bytes = path_size_reader.into_bytes();
 
         // If the TabState file is for a saved file, extract the additinal data
         if is_saved_file {
@@ -334,6 +567,7 @@ impl NotepadTabStat {
             }
 
             // Read `unknown1`
+            let unknown1_offset = reader.position();
             let mut unknown1 = [0u8; 2];
             if let Err(e) = reader.read_exact(&mut unknown1) {
                 return Err(NotepadErrors::ReadError(
@@ -341,6 +575,7 @@ impl NotepadTabStat {
                     "unknown1".to_string(),
                 ));
             }
+            undecoded_fields.push(UndecodedField::new("unknown1", unknown1_offset, &unknown1));
 
             // Read `cursor_start`. This is starting point of the text selection
             let cursor_start = match read_uleb128(reader) {
@@ -367,6 +602,17 @@ impl NotepadTabStat {
             // Read unknown2
             //TODO: Change to config block
             let config_block = ConfigBlock::from_reader(reader)?;
+            // `unknown0` and `unknown1` are the last two bytes of the config block
+            undecoded_fields.push(UndecodedField::new(
+                "ConfigBlock::unknown0",
+                reader.position() - 2,
+                &[config_block.unknown0],
+            ));
+            undecoded_fields.push(UndecodedField::new(
+                "ConfigBlock::unknown1",
+                reader.position() - 1,
+                &[config_block.unknown1],
+            ));
             // let mut unknown2 = [0u8; 6];
             // if let Err(e) = reader.read_exact(&mut unknown2) {
             //     return Err(NotepadErrors::ReadError(
@@ -438,13 +684,12 @@ impl NotepadTabStat {
                 },
             };
 
-            let unsaved_chunks_str = match &unsaved_chunks {
-                Some(data) => Option::Some(data.to_string()),
-                None => Option::None,
-            };
-
             Ok(Self {
+                schema_version: SchemaVersion,
                 tabstate_path: Option::None,
+                tabstate_times: Option::None,
+                tabstate_size: Option::None,
+                notepad_version: Option::None,
                 signature,
                 seq_number,
                 is_saved_file,
@@ -458,17 +703,34 @@ impl NotepadTabStat {
                 unknown1: Option::Some(unknown1),
                 cursor_start: Option::Some(cursor_start),
                 cursor_end: Option::Some(cursor_end),
+                cursor_start_location: Option::None,
+                cursor_end_location: Option::None,
+                selected_text: Option::None,
+                cursor_context: Option::None,
                 config_block,
+                app_settings: Option::None,
                 file_content_size,
                 file_content,
                 contain_unsaved_data,
                 checksum: bytes_to_hex(&checksum.to_vec()),
                 unsaved_chunks,
-                unsaved_chunks_str,
+                reconstructed_content: Option::None,
+                edit_sessions: Option::None,
+                diff_hunks: Option::None,
+                pastes: Option::None,
+                warnings: Option::None,
+                undecoded_fields,
             })
         }
         // File isn't saved to file
         else {
+            // Unsaved tabs have no path, so the meaning of `path_size` is unknown
+            undecoded_fields.push(UndecodedField::new(
+                "path_size",
+                path_size_offset,
+                &path_size_bytes,
+            ));
+
             // Read `cursor_start`. This is starting point of the text selection
             let cursor_start = match read_uleb128(reader) {
                 Ok(cs) => cs,
@@ -492,6 +754,17 @@ impl NotepadTabStat {
             };
             // Read `unknown3`
             let config_block = ConfigBlock::from_reader(reader)?;
+            // `unknown0` and `unknown1` are the last two bytes of the config block
+            undecoded_fields.push(UndecodedField::new(
+                "ConfigBlock::unknown0",
+                reader.position() - 2,
+                &[config_block.unknown0],
+            ));
+            undecoded_fields.push(UndecodedField::new(
+                "ConfigBlock::unknown1",
+                reader.position() - 1,
+                &[config_block.unknown1],
+            ));
 
             // Read `file_content_size`. This is the size of the content in the TabState in chars not bytes
             let file_content_size = match read_uleb128(reader) {
@@ -555,13 +828,12 @@ impl NotepadTabStat {
                 },
             };
 
-            let unsaved_chunks_str = match &unsaved_chunks {
-                Some(data) => Option::Some(data.to_string()),
-                None => Option::None,
-            };
-
             Ok(Self {
+                schema_version: SchemaVersion,
                 tabstate_path: Option::None,
+                tabstate_times: Option::None,
+                tabstate_size: Option::None,
+                notepad_version: Option::None,
                 signature,
                 seq_number,
                 is_saved_file,
@@ -575,13 +847,23 @@ impl NotepadTabStat {
                 unknown1: Option::None,
                 cursor_start: Some(cursor_start),
                 cursor_end: Some(cursor_end),
+                cursor_start_location: Option::None,
+                cursor_end_location: Option::None,
+                selected_text: Option::None,
+                cursor_context: Option::None,
                 file_content_size,
                 config_block,
+                app_settings: Option::None,
                 file_content,
                 contain_unsaved_data,
                 checksum: bytes_to_hex(&checksum.to_vec()),
                 unsaved_chunks,
-                unsaved_chunks_str,
+                reconstructed_content: Option::None,
+                edit_sessions: Option::None,
+                diff_hunks: Option::None,
+                pastes: Option::None,
+                warnings: Option::None,
+                undecoded_fields,
             })
         }
     }
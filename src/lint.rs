@@ -0,0 +1,243 @@
+use crate::{
+    enums::{CRType, Encoding},
+    position::map_position,
+    NotepadTabStat,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Checks for values that parse correctly but are inconsistent with the rest of the record
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LintCode {
+    /// `cursor_start` or `cursor_end` is after the end of the buffer
+    CursorOutOfRange,
+    /// `file_size` doesn't match the size of `file_content` encoded with `encoding` and `cr_type`
+    ContentSizeMismatch,
+    /// `encoding` value isn't known
+    UnknownEncoding,
+    /// `cr_type` value isn't known
+    UnknownCrType,
+    /// An unsaved chunk deletes from or adds to a position after the end of the buffer
+    ChunkOutOfRange,
+    /// `last_write_time` is after the time of parsing
+    FutureTimestamp,
+    /// `last_write_time` is after the last modification time of the `TabState` file
+    WriteTimeAfterTabState,
+    /// `last_write_time` without sub-seconds, which is common for timestomped files. The
+    /// filesystem times aren't checked as copying or extracting the files can zero them
+    ZeroedSubseconds,
+    /// Unsaved chunks are present but `contain_unsaved_data` is false
+    UnsavedDataUnset,
+}
+
+/// How strongly a warning points to a tampered file or a gap in the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintSeverity {
+    /// Also seen in files written by Notepad, only worth a look together with other warnings
+    Info,
+    Warning,
+}
+
+impl LintCode {
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            // Notepad leaves `contain_unsaved_data` unset in all the samples with unsaved chunks
+            LintCode::UnsavedDataUnset => LintSeverity::Info,
+            _ => LintSeverity::Warning,
+        }
+    }
+
+    /// `true` if the check depends on the time it is run at
+    pub fn depends_on_run_time(&self) -> bool {
+        matches!(self, LintCode::FutureTimestamp)
+    }
+}
+
+impl Display for LintCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            LintCode::CursorOutOfRange => "cursor_out_of_range",
+            LintCode::ContentSizeMismatch => "content_size_mismatch",
+            LintCode::UnknownEncoding => "unknown_encoding",
+            LintCode::UnknownCrType => "unknown_cr_type",
+            LintCode::ChunkOutOfRange => "chunk_out_of_range",
+            LintCode::FutureTimestamp => "future_timestamp",
+            LintCode::WriteTimeAfterTabState => "write_time_after_tabstate",
+            LintCode::ZeroedSubseconds => "zeroed_subseconds",
+            LintCode::UnsavedDataUnset => "unsaved_data_unset",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// A suspicious value found by `lint`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct LintWarning {
+    pub code: LintCode,
+    /// The field the warning is about
+    pub field: String,
+    pub message: String,
+}
+
+impl LintWarning {
+    pub fn new(code: LintCode, field: &str, message: String) -> Self {
+        Self {
+            code,
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// Check `record` for values that are structurally valid but inconsistent with each other.
+/// These can point to a tampered file or a gap in the parser
+pub fn lint(record: &NotepadTabStat) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+
+    if let Some(Encoding::UNKNOWN(x)) = record.encoding {
+        warnings.push(LintWarning::new(
+            LintCode::UnknownEncoding,
+            "encoding",
+            format!("Unknown encoding value '{}'", x),
+        ));
+    }
+    if let Some(CRType::UNKNOWN(x)) = record.cr_type {
+        warnings.push(LintWarning::new(
+            LintCode::UnknownCrType,
+            "cr_type",
+            format!("Unknown line ending value '{}'", x),
+        ));
+    }
+
+    // Replay the chunks lengths to find the buffer size each chunk is applied to
+    let mut buffer_len = record.file_content.encode_utf16().count() as u64;
+    let chunks = record
+        .unsaved_chunks
+        .iter()
+        .flat_map(|chunks| chunks.iter());
+    for (index, chunk) in chunks.enumerate() {
+        let end = chunk.position().saturating_add(chunk.num_of_deletion());
+        if end > buffer_len {
+            warnings.push(LintWarning::new(
+                LintCode::ChunkOutOfRange,
+                "unsaved_chunks",
+                format!(
+                    "Chunk {} changes the range {}..{} of a buffer with {} chars",
+                    index,
+                    chunk.position(),
+                    end,
+                    buffer_len
+                ),
+            ));
+        }
+        buffer_len = buffer_len
+            .saturating_sub(chunk.num_of_deletion())
+            .saturating_add(chunk.num_of_addition());
+    }
+
+    for (field, cursor) in [
+        ("cursor_start", record.cursor_start),
+        ("cursor_end", record.cursor_end),
+    ] {
+        if let Some(cursor) = cursor.filter(|cursor| *cursor > buffer_len) {
+            warnings.push(LintWarning::new(
+                LintCode::CursorOutOfRange,
+                field,
+                format!(
+                    "Cursor position {} is after the end of the buffer with {} chars",
+                    cursor, buffer_len
+                ),
+            ));
+        }
+    }
+
+    let has_chunks = record
+        .unsaved_chunks
+        .as_ref()
+        .is_some_and(|chunks| !chunks.is_empty());
+    if has_chunks && !record.contain_unsaved_data {
+        warnings.push(LintWarning::new(
+            LintCode::UnsavedDataUnset,
+            "contain_unsaved_data",
+            "Unsaved chunks are present but the unsaved data flag isn't set".to_string(),
+        ));
+    }
+
+    // Only compare the sizes of unmodified files with encodings that have a known size per char
+    if let (Some(file_size), false, false) =
+        (record.file_size, record.contain_unsaved_data, has_chunks)
+    {
+        let expected = match record.encoding {
+            Some(Encoding::UTF8 | Encoding::UTF8BOM | Encoding::UTF16LE | Encoding::UTF16BE) => {
+                map_position(
+                    &record.file_content,
+                    record.file_content.encode_utf16().count() as u64,
+                    record.encoding.as_ref(),
+                    record.cr_type.as_ref(),
+                )
+                .and_then(|position| position.file_offset)
+            }
+            _ => None,
+        };
+        if let Some(expected) = expected.filter(|expected| *expected != file_size) {
+            warnings.push(LintWarning::new(
+                LintCode::ContentSizeMismatch,
+                "file_size",
+                format!(
+                    "The file size is {} bytes but the saved content is {} bytes",
+                    file_size, expected
+                ),
+            ));
+        }
+    }
+
+    let last_write_time = record.last_write_time.as_ref().map(DateTime::<Utc>::from);
+    let tabstate_times = record.tabstate_times.as_ref();
+    let tabstate_modified = tabstate_times
+        .and_then(|times| times.modified.as_ref())
+        .map(DateTime::<Utc>::from);
+
+    if let Some(last_write_time) = last_write_time {
+        if last_write_time > Utc::now() {
+            warnings.push(LintWarning::new(
+                LintCode::FutureTimestamp,
+                "last_write_time",
+                format!("The last write time {} is in the future", last_write_time),
+            ));
+        }
+    }
+
+    // Notepad updates the `TabState` file after saving the file, the saved file
+    // can't be written by Notepad after the `TabState` file was last modified
+    if let (Some(last_write_time), Some(tabstate_modified)) = (last_write_time, tabstate_modified) {
+        if last_write_time > tabstate_modified {
+            warnings.push(LintWarning::new(
+                LintCode::WriteTimeAfterTabState,
+                "last_write_time",
+                format!(
+                    "The last write time {} is after the TabState file modification time {}",
+                    last_write_time, tabstate_modified
+                ),
+            ));
+        }
+    }
+
+    if let Some(last_write_time) = last_write_time.filter(|t| t.timestamp_subsec_nanos() == 0) {
+        warnings.push(LintWarning::new(
+            LintCode::ZeroedSubseconds,
+            "last_write_time",
+            format!("The timestamp {} has zeroed sub-seconds", last_write_time),
+        ));
+    }
+
+    warnings
+}
@@ -0,0 +1,305 @@
+use crate::{
+    analysis::EditKind,
+    errors::NotepadErrors,
+    lint::LintWarning,
+    output::{enum_text, OutputSink},
+    schema::SCHEMA_VERSION,
+    summary::TabStateSummary,
+    timestamps::{datetime_text, filetime_text},
+    NotepadTabStat,
+};
+use std::{fmt::Display, io::Write};
+
+/// Characters with a meaning in Markdown, escaped with a backslash in text
+const SPECIAL_CHARS: [char; 12] = ['\\', '`', '*', '_', '[', ']', '<', '>', '|', '#', '~', '!'];
+
+/// Escape the Markdown characters in `text` so it is shown as is
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Length of the longest run of backticks in `text`
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or_default()
+}
+
+/// `text` as a fenced code block. The fence is longer than any run of backticks in `text` so it
+/// can't be closed early, and the line breaks are normalized to `\n` so the output diffs cleanly
+pub fn fenced_block(text: &str) -> String {
+    let fence = "`".repeat(3.max(longest_backtick_run(text) + 1));
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let newline = match text.is_empty() || text.ends_with('\n') {
+        true => "",
+        false => "\n",
+    };
+    format!("{}text\n{}{}{}\n", fence, text, newline, fence)
+}
+
+/// `text` as inline code in a table cell. Line breaks are shown as `↵`
+fn inline_code(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let text = text
+        .replace("\r\n", "\u{21B5}")
+        .replace(['\r', '\n'], "\u{21B5}")
+        .replace('|', "\\|");
+    let delimiter = "`".repeat(longest_backtick_run(&text) + 1);
+    let padding = match text.starts_with('`') || text.ends_with('`') {
+        true => " ",
+        false => "",
+    };
+    format!("{}{}{}{}{}", delimiter, padding, text, padding, delimiter)
+}
+
+fn markdown_error(e: std::io::Error, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// Rows of a two column table, rows without a value are left out
+#[derive(Default)]
+struct Metadata(String);
+
+impl Metadata {
+    fn row<T: Display>(&mut self, name: &str, value: Option<T>) -> &mut Self {
+        if let Some(value) = value {
+            self.0.push_str(&format!(
+                "| {} | {} |\n",
+                name,
+                inline_code(&value.to_string())
+            ));
+        }
+        self
+    }
+
+    fn table(&self) -> String {
+        format!("| Field | Value |\n| --- | --- |\n{}\n", self.0)
+    }
+}
+
+/// Counts of the unsaved chunks and a row for each edit session
+fn edits_summary(record: &NotepadTabStat) -> Option<String> {
+    let chunks = record.unsaved_chunks.as_ref().filter(|c| !c.is_empty())?;
+    // The counts are read from the file and can overflow
+    let added = chunks
+        .iter()
+        .map(|c| c.num_of_addition())
+        .fold(0u64, u64::saturating_add);
+    let deleted = chunks
+        .iter()
+        .map(|c| c.num_of_deletion())
+        .fold(0u64, u64::saturating_add);
+    let invalid = chunks
+        .iter()
+        .filter(|c| c.checksum_valid() == Some(false))
+        .count();
+    let sessions = record.edit_sessions.as_deref().unwrap_or_default();
+    let mut summary = format!(
+        "{} unsaved chunks in {} edits, {} characters added and {} characters deleted.",
+        chunks.len(),
+        sessions.len(),
+        added,
+        deleted
+    );
+    if invalid > 0 {
+        summary.push_str(&format!(" {} chunks have an invalid checksum.", invalid));
+    }
+    summary.push_str("\n\n| # | Edit | Chunks | Position | Added | Deleted |\n| --- | --- | --- | --- | --- | --- |\n");
+    for (index, session) in sessions.iter().enumerate() {
+        let deleted = match (&session.deleted_text, &session.kind) {
+            (_, EditKind::Typed) => String::new(),
+            (Some(text), _) => inline_code(text),
+            (None, _) => format!("{} characters", session.deleted),
+        };
+        summary.push_str(&format!(
+            "| {} | {} | {}-{} | {} | {} | {} |\n",
+            index,
+            enum_text(Some(&session.kind)).unwrap_or_default(),
+            session.first_chunk,
+            session.last_chunk,
+            session.position,
+            inline_code(session.text.as_deref().unwrap_or_default()),
+            deleted
+        ));
+    }
+    Some(summary)
+}
+
+/// A Markdown document with a section for each tab with its metadata, the content, the
+/// reconstructed buffer and a summary of the unsaved edits. Nothing depends on the time of the
+/// run so the output of the same files is the same. The access time of the `TabState` file is
+/// left out as reading the file can update it, and so are the warnings that depend on the time
+/// of the run like `LintCode::FutureTimestamp`
+pub struct MarkdownSink {
+    output: Box<dyn Write>,
+}
+
+impl MarkdownSink {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self { output }
+    }
+
+    fn write(&mut self, markdown: &str) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .write_all(markdown.as_bytes())
+            .map_err(|e| markdown_error(e, "Unable to write the report"))
+    }
+}
+
+impl OutputSink for MarkdownSink {
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        let header = format!(
+            "# Notepad TabState report\n\nnotepad\\_parser v{}, schema version {}\n\n",
+            env!("CARGO_PKG_VERSION"),
+            SCHEMA_VERSION
+        );
+        self.write(&header)
+    }
+
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        let title = match (&record.path, record.tab_guid()) {
+            (Some(path), _) => path.clone(),
+            (None, Some(guid)) => format!("Unsaved tab {}", guid),
+            (None, None) => "Unsaved tab".to_string(),
+        };
+        let times = record.tabstate_times.as_ref();
+        let config = &record.config_block;
+        let mut metadata = Metadata::default();
+        metadata
+            .row("TabState path", record.tabstate_path.as_ref())
+            .row("Tab GUID", record.tab_guid())
+            .row("Saved file", Some(record.is_saved_file))
+            .row("Path", record.path.as_ref())
+            .row("File size", record.file_size)
+            .row("Encoding", enum_text(record.encoding.as_ref()))
+            .row("Line endings", enum_text(record.cr_type.as_ref()))
+            .row(
+                "Last write time",
+                record.last_write_time.as_ref().map(filetime_text),
+            )
+            .row("File hash (SHA256)", record.file_hash.as_ref())
+            .row("Checksum (CRC32)", Some(&record.checksum))
+            .row(
+                "TabState modified",
+                times.and_then(|t| t.modified.as_ref()).map(filetime_text),
+            )
+            .row(
+                "TabState changed",
+                times.and_then(|t| t.changed.as_ref()).map(filetime_text),
+            )
+            .row(
+                "TabState created",
+                times.and_then(|t| t.created.as_ref()).map(filetime_text),
+            )
+            .row("Sequence number", Some(record.seq_number))
+            .row("Cursor start", record.cursor_start)
+            .row("Cursor end", record.cursor_end)
+            .row("Word wrap", Some(config.word_wrap))
+            .row("Right-to-left", Some(config.rtl))
+            .row("Show unicode", Some(config.show_unicode))
+            .row("Config version", Some(config.version))
+            .row("Notepad version", record.notepad_version.as_ref());
+
+        let mut section = format!("## {}\n\n{}", escape_markdown(&title), metadata.table());
+        let warnings = record
+            .warnings
+            .iter()
+            .flatten()
+            .filter(|warning| !warning.code.depends_on_run_time())
+            .collect::<Vec<&LintWarning>>();
+        if !warnings.is_empty() {
+            section.push_str("### Warnings\n\n");
+            for warning in warnings {
+                section.push_str(&format!("- {}\n", escape_markdown(&warning.to_string())));
+            }
+            section.push('\n');
+        }
+        let content_title = match record.is_saved_file {
+            true => "Saved content",
+            false => "Content",
+        };
+        section.push_str(&format!(
+            "### {}\n\n{}\n",
+            content_title,
+            fenced_block(&record.file_content)
+        ));
+        if let Some(content) = record.reconstructed_content() {
+            section.push_str(&format!(
+                "### Reconstructed buffer\n\n{}\n",
+                fenced_block(content)
+            ));
+        }
+        if let Some(summary) = edits_summary(record) {
+            section.push_str(&format!("### Unsaved edits\n\n{}\n", summary));
+        }
+        self.write(&section)
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        let mut metadata = Metadata::default();
+        metadata
+            .row("Tabs", Some(summary.tabs_count))
+            .row("Saved tabs", Some(summary.saved_tabs))
+            .row("Unsaved tabs", Some(summary.unsaved_tabs))
+            .row(
+                "Last activity",
+                summary.last_activity.as_ref().map(datetime_text),
+            );
+        let mut section = format!(
+            "## TabState directory {}\n\n{}| Tab GUID | Path | Saved file | Unsaved chunks | Last activity |\n| --- | --- | --- | --- | --- |\n",
+            escape_markdown(&summary.tabstate_dir),
+            metadata.table()
+        );
+        for tab in &summary.tabs {
+            section.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                inline_code(&tab.tab_guid),
+                inline_code(tab.path.as_deref().unwrap_or_default()),
+                tab.is_saved_file,
+                tab.has_unsaved_chunks,
+                tab.last_activity
+                    .as_ref()
+                    .map(datetime_text)
+                    .unwrap_or_default()
+            ));
+        }
+        section.push('\n');
+        for error in &summary.errors {
+            section.push_str(&format!("- {}\n", escape_markdown(error)));
+        }
+        if !summary.errors.is_empty() {
+            section.push('\n');
+        }
+        self.write(&section)
+    }
+
+    /// Files that failed to parse get a section with the error
+    fn error(
+        &mut self,
+        path: &str,
+        error: &NotepadErrors,
+    ) -> std::result::Result<(), NotepadErrors> {
+        let section = format!(
+            "## Failed to parse {}\n\n{}\n",
+            escape_markdown(path),
+            fenced_block(&error.to_string())
+        );
+        self.write(&section)
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .flush()
+            .map_err(|e| markdown_error(e, "Unable to flush the output"))
+    }
+}
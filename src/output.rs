@@ -0,0 +1,655 @@
+use crate::{
+    enums::{CRType, Encoding},
+    errors::NotepadErrors,
+    research::ResearchRecord,
+    schema::{filetime_schema, SchemaVersion},
+    summary::TabStateSummary,
+    timestamps::{copy_filetime, datetime_text, serialize_filetime, FileSystemTimes},
+    unsaved_chunks::EditOperation,
+    NotepadTabStat,
+};
+use csv::WriterBuilder;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    io::{self, Write},
+    mem,
+    path::PathBuf,
+};
+use winparsingtools::date_time::FileTime;
+
+/// Byte order mark written at the start of the CSV output with `SinkOptions::csv_bom`
+pub const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Characters that make a spreadsheet treat a cell as a formula
+const FORMULA_PREFIXES: [char; 5] = ['=', '+', '-', '@', '\t'];
+
+/// Make `value` safe to open in a spreadsheet. Backslashes are escaped as `\\`, line breaks as
+/// `\r` and `\n` and values starting with a formula character are prefixed with `'` so they are
+/// shown as text
+pub fn safe_cell(value: &str) -> String {
+    let value = value
+        .replace('\\', "\\\\")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n");
+    match value.starts_with(FORMULA_PREFIXES) {
+        true => format!("'{}", value),
+        false => value,
+    }
+}
+
+/// A parsed tab flattened to a single CSV row
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CsvRecord {
+    pub schema_version: SchemaVersion,
+    pub tabstate_path: Option<String>,
+    #[schemars(schema_with = "filetime_schema")]
+    #[serde(serialize_with = "serialize_filetime")]
+    pub tabstate_modified: Option<FileTime>,
+    #[schemars(schema_with = "filetime_schema")]
+    #[serde(serialize_with = "serialize_filetime")]
+    pub tabstate_accessed: Option<FileTime>,
+    #[schemars(schema_with = "filetime_schema")]
+    #[serde(serialize_with = "serialize_filetime")]
+    pub tabstate_changed: Option<FileTime>,
+    #[schemars(schema_with = "filetime_schema")]
+    #[serde(serialize_with = "serialize_filetime")]
+    pub tabstate_created: Option<FileTime>,
+    pub notepad_version: Option<String>,
+    pub is_saved_file: bool,
+    pub path_size: u64,
+    pub path: Option<String>,
+    pub file_size: Option<u64>,
+    pub encoding: Option<Encoding>,
+    pub cr_type: Option<CRType>,
+    #[schemars(schema_with = "filetime_schema")]
+    #[serde(serialize_with = "serialize_filetime")]
+    pub last_write_time: Option<FileTime>,
+    pub file_hash: Option<String>,
+    pub cursor_start: Option<u64>,
+    pub cursor_end: Option<u64>,
+    pub cursor_start_location: Option<String>,
+    pub cursor_end_location: Option<String>,
+    pub selected_text: Option<String>,
+    pub reconstructed_selected_text: Option<String>,
+    pub cursor_context: Option<String>,
+    pub reconstructed_cursor_context: Option<String>,
+    pub word_wrap: bool,
+    pub rtl: bool,
+    pub show_unicode: bool,
+    pub version: u64,
+    pub file_content_size: u64,
+    pub file_content: String,
+    pub contain_unsaved_data: bool,
+    pub checksum: String,
+    pub edit_sessions: Option<String>,
+    pub edit_locations: Option<String>,
+    pub pastes: Option<String>,
+    pub warnings: Option<String>,
+    pub raw: String,
+}
+
+impl From<&NotepadTabStat> for CsvRecord {
+    fn from(value: &NotepadTabStat) -> Self {
+        let json_data = match serde_json::to_string(&value) {
+            Ok(data) => data,
+            Err(e) => e.to_string(),
+        };
+        let edit_locations = value.unsaved_chunks.as_ref().map(|chunks| {
+            chunks
+                .iter()
+                .map(|chunk| {
+                    let action = match chunk.operation() {
+                        EditOperation::Insert { .. } => "insert",
+                        EditOperation::Delete { .. } => "delete",
+                        EditOperation::Replace { .. } => "replace",
+                        EditOperation::NoOp { .. } => "no-op",
+                    };
+                    match chunk.location() {
+                        Some(location) => format!("{} at {}", action, location),
+                        None => format!("{} at offset {}", action, chunk.position()),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("; ")
+        });
+        let pastes = value.pastes.as_ref().map(|pastes| {
+            pastes
+                .iter()
+                .map(|paste| match &paste.location {
+                    Some(location) => format!(
+                        "pasted {} chars at {}: {}",
+                        paste.length, location, paste.text
+                    ),
+                    None => format!(
+                        "pasted {} chars at offset {}: {}",
+                        paste.length, paste.position, paste.text
+                    ),
+                })
+                .collect::<Vec<String>>()
+                .join("; ")
+        });
+        let edit_sessions = value.edit_sessions.as_ref().map(|sessions| {
+            sessions
+                .iter()
+                .map(|session| session.to_string())
+                .collect::<Vec<String>>()
+                .join(", then ")
+        });
+        let warnings = value.warnings.as_ref().map(|warnings| {
+            warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        });
+        let tabstate_times = value.tabstate_times.as_ref();
+        let tabstate_time = |time: fn(&FileSystemTimes) -> &Option<FileTime>| {
+            tabstate_times.and_then(|times| time(times).as_ref().map(copy_filetime))
+        };
+        Self {
+            schema_version: SchemaVersion,
+            tabstate_path: value.tabstate_path.clone(),
+            tabstate_modified: tabstate_time(|times| &times.modified),
+            tabstate_accessed: tabstate_time(|times| &times.accessed),
+            tabstate_changed: tabstate_time(|times| &times.changed),
+            tabstate_created: tabstate_time(|times| &times.created),
+            notepad_version: value.notepad_version.clone(),
+            is_saved_file: value.is_saved_file,
+            path_size: value.path_size,
+            path: value.path.clone(),
+            file_size: value.file_size,
+            encoding: value.encoding.clone(),
+            cr_type: value.cr_type.clone(),
+            last_write_time: value.last_write_time.as_ref().map(copy_filetime),
+            file_hash: value.file_hash.clone(),
+            cursor_start: value.cursor_start,
+            cursor_end: value.cursor_end,
+            cursor_start_location: value.cursor_start_location.as_ref().map(|l| l.to_string()),
+            cursor_end_location: value.cursor_end_location.as_ref().map(|l| l.to_string()),
+            selected_text: value.selected_text.clone().and_then(|t| t.saved),
+            reconstructed_selected_text: value.selected_text.clone().and_then(|t| t.reconstructed),
+            cursor_context: value.cursor_context.clone().and_then(|t| t.saved),
+            reconstructed_cursor_context: value
+                .cursor_context
+                .clone()
+                .and_then(|t| t.reconstructed),
+            word_wrap: value.config_block.word_wrap,
+            rtl: value.config_block.rtl,
+            show_unicode: value.config_block.show_unicode,
+            version: value.config_block.version,
+            file_content_size: value.file_content_size,
+            file_content: value.file_content.clone(),
+            contain_unsaved_data: value.contain_unsaved_data,
+            checksum: value.checksum.clone(),
+            edit_sessions,
+            edit_locations,
+            pastes,
+            warnings,
+            raw: json_data,
+        }
+    }
+}
+
+/// A single unsaved chunk, joined to `CsvRecord` by `tabstate_path`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ChunkCsvRecord {
+    pub schema_version: SchemaVersion,
+    pub tabstate_path: Option<String>,
+    pub tab_guid: Option<String>,
+    pub chunk_index: usize,
+    pub position: u64,
+    pub num_of_deletion: u64,
+    pub num_of_addition: u64,
+    pub data: Option<String>,
+    pub checksum: String,
+    pub checksum_valid: Option<bool>,
+    pub location: Option<String>,
+}
+
+impl ChunkCsvRecord {
+    pub fn from_record(value: &NotepadTabStat) -> Vec<Self> {
+        let tab_guid = value.tab_guid();
+        value
+            .unsaved_chunks
+            .iter()
+            .flat_map(|chunks| chunks.iter().enumerate())
+            .map(|(index, chunk)| Self {
+                schema_version: SchemaVersion,
+                tabstate_path: value.tabstate_path.clone(),
+                tab_guid: tab_guid.clone(),
+                chunk_index: index,
+                position: chunk.position(),
+                num_of_deletion: chunk.num_of_deletion(),
+                num_of_addition: chunk.num_of_addition(),
+                data: chunk.data().map(|data| data.to_string()),
+                checksum: chunk.checksum().to_string(),
+                checksum_valid: chunk.checksum_valid(),
+                location: chunk.location().map(|location| location.to_string()),
+            })
+            .collect()
+    }
+}
+
+/// A `TabState` directory summary flattened to a single CSV row
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SummaryCsvRecord {
+    pub schema_version: SchemaVersion,
+    pub tabstate_dir: String,
+    pub tabs_count: usize,
+    pub saved_tabs: usize,
+    pub unsaved_tabs: usize,
+    pub last_activity: Option<String>,
+    pub tabs: String,
+    pub raw: String,
+}
+
+impl From<&TabStateSummary> for SummaryCsvRecord {
+    fn from(value: &TabStateSummary) -> Self {
+        let json_data = match serde_json::to_string(&value) {
+            Ok(data) => data,
+            Err(e) => e.to_string(),
+        };
+        Self {
+            schema_version: SchemaVersion,
+            tabstate_dir: value.tabstate_dir.clone(),
+            tabs_count: value.tabs_count,
+            saved_tabs: value.saved_tabs,
+            unsaved_tabs: value.unsaved_tabs,
+            last_activity: value.last_activity.as_ref().map(datetime_text),
+            tabs: value
+                .tabs
+                .iter()
+                .map(|tab| tab.tab_guid.clone())
+                .collect::<Vec<String>>()
+                .join(";"),
+            raw: json_data,
+        }
+    }
+}
+
+/// Options passed to the sinks created by `SinkRegistry`
+#[derive(Debug, Clone)]
+pub struct SinkOptions {
+    /// Include the undecoded fields of the records that have them
+    pub research: bool,
+    pub csv_delimiter: u8,
+    /// Neutralize formulas and escape backslashes and line breaks in the CSV cells, see `safe_cell`
+    pub safe_csv: bool,
+    /// Write `UTF8_BOM` before the CSV headers
+    pub csv_bom: bool,
+    /// Path of the file the output is written to, `None` for a stream like stdout. Sinks that
+    /// can't write to a stream, like `sqlite`, write to the file directly
+    pub output_path: Option<PathBuf>,
+}
+
+impl Default for SinkOptions {
+    fn default() -> Self {
+        Self {
+            research: false,
+            csv_delimiter: b',',
+            safe_csv: false,
+            csv_bom: false,
+            output_path: None,
+        }
+    }
+}
+
+/// Receives the results of a run. `begin` is called before the first record and `finish`
+/// after the last one, the sink must not be used after `finish`
+pub trait OutputSink {
+    /// Write anything needed before the records, e.g. a header
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    /// Write a parsed tab
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors>;
+
+    /// Write the summary of a `TabState` directory
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors>;
+
+    /// Called for every file that couldn't be parsed. Ignored by default
+    fn error(
+        &mut self,
+        _path: &str,
+        _error: &NotepadErrors,
+    ) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    /// Flush the buffered output
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors>;
+}
+
+/// Enum values as their JSON names, e.g. `UTF8`
+pub fn enum_text<T: Serialize>(value: Option<&T>) -> Option<String> {
+    value.map(|value| match serde_json::to_value(value) {
+        Ok(Value::String(text)) => text,
+        Ok(value) => value.to_string(),
+        Err(e) => e.to_string(),
+    })
+}
+
+fn output_error(e: impl ToString, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// One JSON object per line
+pub struct JsonlSink {
+    output: Box<dyn Write>,
+    research: bool,
+}
+
+impl JsonlSink {
+    pub fn new(output: Box<dyn Write>, options: &SinkOptions) -> Self {
+        Self {
+            output,
+            research: options.research,
+        }
+    }
+
+    fn write_json<T: Serialize>(&mut self, record: &T) -> std::result::Result<(), NotepadErrors> {
+        let json = match serde_json::to_string(record) {
+            Ok(json) => json,
+            Err(e) => return Err(output_error(e, "Unable to convert to JSON")),
+        };
+        writeln!(self.output, "{}", json)
+            .map_err(|e| output_error(e, "Unable to write the JSON line"))
+    }
+}
+
+impl OutputSink for JsonlSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        match self.research && !record.undecoded_fields.is_empty() {
+            true => self.write_json(&ResearchRecord::from(record)),
+            false => self.write_json(record),
+        }
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        self.write_json(summary)
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .flush()
+            .map_err(|e| output_error(e, "Unable to flush the output"))
+    }
+}
+
+/// CSV rows streamed through a single writer. Summaries have no chunks so the chunks mode writes
+/// the same summary rows
+pub struct CsvSink {
+    builder: WriterBuilder,
+    writer: csv::Writer<Box<dyn Write>>,
+    /// Write one `ChunkCsvRecord` per unsaved chunk instead of one `CsvRecord` per tab
+    chunks: bool,
+    research: bool,
+    safe: bool,
+    bom: bool,
+    /// The headers are written by `writer` unless `safe` is set
+    headers_written: bool,
+}
+
+impl CsvSink {
+    pub fn new(output: Box<dyn Write>, options: &SinkOptions) -> Self {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(options.csv_delimiter)
+            .has_headers(!options.safe_csv);
+        Self {
+            writer: builder.from_writer(output),
+            builder,
+            chunks: false,
+            research: options.research,
+            safe: options.safe_csv,
+            bom: options.csv_bom,
+            headers_written: false,
+        }
+    }
+
+    /// Write one row per unsaved chunk, see `ChunkCsvRecord`
+    pub fn chunks(output: Box<dyn Write>, options: &SinkOptions) -> Self {
+        Self {
+            chunks: true,
+            ..Self::new(output, options)
+        }
+    }
+
+    fn write_row<T: Serialize>(&mut self, row: &T) -> std::result::Result<(), NotepadErrors> {
+        if !self.safe {
+            return self
+                .writer
+                .serialize(row)
+                .map_err(|e| output_error(e, "Unable to write the CSV row"));
+        }
+
+        let fields = match serde_json::to_value(row) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(value) => {
+                return Err(output_error(
+                    format!("Expected a CSV record, found '{}'", value),
+                    "Unable to write the CSV row",
+                ))
+            }
+            Err(e) => return Err(output_error(e, "Unable to write the CSV row")),
+        };
+        if !self.headers_written {
+            self.writer
+                .write_record(fields.keys())
+                .map_err(|e| output_error(e, "Unable to write the CSV headers"))?;
+            self.headers_written = true;
+        }
+        let cells = fields.values().map(|value| match value {
+            Value::Null => String::new(),
+            Value::String(value) => safe_cell(value),
+            value => safe_cell(&value.to_string()),
+        });
+        self.writer
+            .write_record(cells)
+            .map_err(|e| output_error(e, "Unable to write the CSV row"))
+    }
+}
+
+impl OutputSink for CsvSink {
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        if !self.bom {
+            return Ok(());
+        }
+        // Nothing is written before `begin`, take the output from the writer to write the BOM
+        let placeholder = self
+            .builder
+            .from_writer(Box::new(io::sink()) as Box<dyn Write>);
+        let mut output = match mem::replace(&mut self.writer, placeholder).into_inner() {
+            Ok(output) => output,
+            Err(e) => return Err(output_error(e.error(), "Unable to write the BOM")),
+        };
+        let result = output
+            .write_all(UTF8_BOM)
+            .map_err(|e| output_error(e, "Unable to write the BOM"));
+        self.writer = self.builder.from_writer(output);
+        result
+    }
+
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        if self.chunks {
+            return ChunkCsvRecord::from_record(record)
+                .iter()
+                .try_for_each(|row| self.write_row(row));
+        }
+
+        let mut csv_record = CsvRecord::from(record);
+        if self.research && !record.undecoded_fields.is_empty() {
+            csv_record.raw = serde_json::to_string(&ResearchRecord::from(record))
+                .unwrap_or_else(|e| e.to_string());
+        }
+        self.write_row(&csv_record)
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        self.write_row(&SummaryCsvRecord::from(summary))
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.writer
+            .flush()
+            .map_err(|e| output_error(e, "Unable to flush the output"))
+    }
+}
+
+/// The diff between the saved content and the unsaved buffer of each tab as plain text. Summaries
+/// aren't written
+pub struct DiffSink {
+    output: Box<dyn Write>,
+    /// Write word level diffs instead of unified diffs
+    word: bool,
+}
+
+impl DiffSink {
+    pub fn new(output: Box<dyn Write>, word: bool) -> Self {
+        Self { output, word }
+    }
+}
+
+impl OutputSink for DiffSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        let diff = match self.word {
+            true => record.word_diff().map(|diff| {
+                format!(
+                    "=== {} ===\n{}\n",
+                    record.tabstate_path.as_deref().unwrap_or_default(),
+                    diff
+                )
+            }),
+            false => record.unified_diff(),
+        };
+        match diff {
+            Some(diff) => write!(self.output, "{}", diff)
+                .map_err(|e| output_error(e, "Unable to write the diff")),
+            None => Ok(()),
+        }
+    }
+
+    fn summary(&mut self, _summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .flush()
+            .map_err(|e| output_error(e, "Unable to flush the output"))
+    }
+}
+
+/// Creates a sink writing to `output`, it can capture the state the sink needs
+pub type SinkFactory = Box<
+    dyn Fn(Box<dyn Write>, &SinkOptions) -> std::result::Result<Box<dyn OutputSink>, NotepadErrors>
+        + Send
+        + Sync,
+>;
+
+/// Output sinks by the name of their output format. `default()` has the built-in `jsonl`,
+/// `csv`, `csv-chunks`, `html`, `markdown`, `bodyfile`, `l2tcsv` and `tln` sinks, `sqlite` with the `sqlite` feature, `parquet` and `arrow`
+/// with the `parquet` feature and `xlsx` with the `xlsx` feature
+pub struct SinkRegistry {
+    /// In the order they were registered
+    sinks: Vec<(&'static str, SinkFactory)>,
+}
+
+impl SinkRegistry {
+    /// A registry without any sinks
+    pub fn new() -> Self {
+        Self { sinks: vec![] }
+    }
+
+    /// Add a sink for the output format `name`, replacing the sink registered with the same name
+    pub fn register<F>(&mut self, name: &'static str, factory: F)
+    where
+        F: Fn(
+                Box<dyn Write>,
+                &SinkOptions,
+            ) -> std::result::Result<Box<dyn OutputSink>, NotepadErrors>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let factory: SinkFactory = Box::new(factory);
+        match self.sinks.iter_mut().find(|(sink, _)| *sink == name) {
+            Some(sink) => sink.1 = factory,
+            None => self.sinks.push((name, factory)),
+        }
+    }
+
+    /// Names of the registered output formats
+    pub fn names(&self) -> Vec<&'static str> {
+        self.sinks.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Create the sink of the output format `name`
+    pub fn create(
+        &self,
+        name: &str,
+        output: Box<dyn Write>,
+        options: &SinkOptions,
+    ) -> std::result::Result<Box<dyn OutputSink>, NotepadErrors> {
+        match self.sinks.iter().find(|(sink, _)| *sink == name) {
+            Some((_, factory)) => factory(output, options),
+            None => Err(NotepadErrors::UnexpectedValue(
+                self.names().join(", "),
+                name.to_string(),
+                "output_format".to_string(),
+            )),
+        }
+    }
+}
+
+impl Default for SinkRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("jsonl", |output, options| {
+            Ok(Box::new(JsonlSink::new(output, options)))
+        });
+        registry.register("csv", |output, options| {
+            Ok(Box::new(CsvSink::new(output, options)))
+        });
+        registry.register("csv-chunks", |output, options| {
+            Ok(Box::new(CsvSink::chunks(output, options)))
+        });
+        registry.register("html", |output, _| {
+            Ok(Box::new(crate::html::HtmlSink::new(output)))
+        });
+        registry.register("markdown", |output, _| {
+            Ok(Box::new(crate::markdown::MarkdownSink::new(output)))
+        });
+        registry.register("bodyfile", |output, _| {
+            Ok(Box::new(crate::timeline::BodyfileSink::new(output)))
+        });
+        registry.register("l2tcsv", |output, _| {
+            Ok(Box::new(crate::timeline::L2tCsvSink::new(output)))
+        });
+        registry.register("tln", |output, _| {
+            Ok(Box::new(crate::timeline::TlnSink::new(output)))
+        });
+        #[cfg(feature = "sqlite")]
+        registry.register("sqlite", |output, options| {
+            Ok(Box::new(crate::sqlite::SqliteSink::new(output, options)))
+        });
+        #[cfg(feature = "parquet")]
+        registry.register("parquet", |output, options| {
+            Ok(Box::new(crate::columnar::ParquetSink::new(
+                output, options,
+            )?))
+        });
+        #[cfg(feature = "parquet")]
+        registry.register("arrow", |output, options| {
+            Ok(Box::new(crate::columnar::ArrowSink::new(output, options)?))
+        });
+        #[cfg(feature = "xlsx")]
+        registry.register("xlsx", |output, _| {
+            Ok(Box::new(crate::xlsx::XlsxSink::new(output)?))
+        });
+        registry
+    }
+}
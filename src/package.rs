@@ -0,0 +1,173 @@
+use crate::errors::NotepadErrors;
+use glob::{glob, Pattern};
+use serde::Serialize;
+use std::{fs, path::Path};
+
+/// Package family name of Windows Notepad
+pub const NOTEPAD_PACKAGE_NAME: &str = "Microsoft.WindowsNotepad";
+/// Publisher ID of Windows Notepad package
+pub const NOTEPAD_PUBLISHER_ID: &str = "8wekyb3d8bbwe";
+
+/// Installed Notepad package information
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct NotepadPackage {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+    /// Path of the manifest or the package folder the version is extracted from
+    pub source: String,
+}
+
+impl NotepadPackage {
+    /// Parse the `Identity` element of an `AppxManifest.xml` file
+    pub fn from_manifest(path: &str) -> std::result::Result<Self, NotepadErrors> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) => return Err(NotepadErrors::FileOpen(e.to_string(), path.to_string())),
+        };
+        let manifest = String::from_utf8_lossy(&data);
+
+        let identity = match manifest.find("<Identity") {
+            Some(start) => {
+                let identity = &manifest[start..];
+                &identity[..identity.find('>').unwrap_or(identity.len())]
+            }
+            None => {
+                return Err(NotepadErrors::UnexpectedValue(
+                    "<Identity>".to_string(),
+                    "None".to_string(),
+                    "AppxManifest::Identity".to_string(),
+                ))
+            }
+        };
+
+        let name = xml_attribute(identity, "Name").unwrap_or_default();
+        if name != NOTEPAD_PACKAGE_NAME {
+            return Err(NotepadErrors::UnexpectedValue(
+                NOTEPAD_PACKAGE_NAME.to_string(),
+                name,
+                "AppxManifest::Identity::Name".to_string(),
+            ));
+        }
+
+        match xml_attribute(identity, "Version") {
+            Some(version) => Ok(Self {
+                name,
+                version,
+                architecture: xml_attribute(identity, "ProcessorArchitecture"),
+                source: path.to_string(),
+            }),
+            None => Err(NotepadErrors::UnexpectedValue(
+                "Version".to_string(),
+                "None".to_string(),
+                "AppxManifest::Identity::Version".to_string(),
+            )),
+        }
+    }
+
+    /// Parse the package full name used for the install folder name,
+    /// e.g. `Microsoft.WindowsNotepad_11.2407.9.0_x64__8wekyb3d8bbwe`
+    pub fn from_full_name(full_name: &str, source: &str) -> Option<Self> {
+        let mut parts = full_name.split('_');
+        let name = parts.next()?;
+        let version = parts.next()?;
+        let architecture = parts.next().filter(|arch| !arch.is_empty());
+        if name != NOTEPAD_PACKAGE_NAME || parse_version(version).is_none() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            architecture: architecture.map(|arch| arch.to_string()),
+            source: source.to_string(),
+        })
+    }
+
+    /// Find the installed Notepad package in `path`. `path` can be the `AppxManifest.xml` file,
+    /// the package install folder or an evidence root containing `Program Files\WindowsApps`.
+    /// If multiple versions are installed the latest one is returned
+    pub fn detect(path: &str) -> std::result::Result<Self, NotepadErrors> {
+        let root = Path::new(path);
+        if root.is_file() {
+            return Self::from_manifest(path);
+        }
+
+        let mut candidates = vec![root.to_path_buf()];
+        // Escape the root so the glob characters in its path are matched literally
+        let escaped_root = Pattern::escape(path);
+        for pattern in [
+            format!("{}_*", NOTEPAD_PACKAGE_NAME),
+            format!("Program Files/WindowsApps/{}_*", NOTEPAD_PACKAGE_NAME),
+            format!("*/Program Files/WindowsApps/{}_*", NOTEPAD_PACKAGE_NAME),
+        ] {
+            let pattern = Path::new(&escaped_root)
+                .join(pattern)
+                .to_string_lossy()
+                .to_string();
+            if let Ok(paths) = glob(&pattern) {
+                candidates.extend(paths.flatten());
+            }
+        }
+
+        let mut packages = vec![];
+        for candidate in candidates.iter().filter(|c| c.is_dir()) {
+            let manifest = candidate.join("AppxManifest.xml");
+            if manifest.is_file() {
+                if let Ok(package) = Self::from_manifest(&manifest.to_string_lossy()) {
+                    packages.push(package);
+                    continue;
+                }
+            }
+            if let Some(folder_name) = candidate.file_name() {
+                if let Some(package) = Self::from_full_name(
+                    &folder_name.to_string_lossy(),
+                    &candidate.to_string_lossy(),
+                ) {
+                    packages.push(package);
+                }
+            }
+        }
+
+        packages
+            .into_iter()
+            .max_by_key(|package| parse_version(&package.version))
+            .ok_or_else(|| {
+                NotepadErrors::Generic(
+                    "Notepad package not found".to_string(),
+                    "NotepadPackage::detect".to_string(),
+                    path.to_string(),
+                )
+            })
+    }
+}
+
+/// Parse a package version `Major.Minor.Build.Revision`
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
+/// Get the value of the attribute `name` from an XML element
+fn xml_attribute(element: &str, name: &str) -> Option<String> {
+    let mut rest = element;
+    while let Some(index) = rest.find(name) {
+        let before = rest[..index].chars().last();
+        let after = rest[index + name.len()..].trim_start();
+        rest = &rest[index + name.len()..];
+        if !matches!(before, Some(c) if c.is_whitespace()) || !after.starts_with('=') {
+            continue;
+        }
+        let value = after[1..].trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value = &value[1..];
+        return value.find(quote).map(|end| value[..end].to_string());
+    }
+    None
+}
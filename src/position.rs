@@ -0,0 +1,425 @@
+use crate::enums::{CRType, Encoding};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// A position in Notepad's buffer expressed in different units.
+/// Notepad stores positions (`cursor_start`, `cursor_end` and `UnsavedChunk::position`) as UTF-16 code units
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TextPosition {
+    /// Offset in UTF-16 code units, as stored in the `TabState` file
+    pub utf16_offset: u64,
+    /// Offset in Unicode scalar values (surrogate pairs count as one character)
+    pub char_index: u64,
+    /// Offset in bytes when the buffer is encoded as UTF-8
+    pub utf8_offset: u64,
+    /// Line number, starting from 1
+    pub line: u64,
+    /// Column in characters, starting from 1
+    pub column: u64,
+    /// Offset in bytes in the file on disk based on `encoding` and `cr_type`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_offset: Option<u64>,
+}
+
+impl Display for TextPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Returns the BOM size and the size of the code unit for `encoding`
+fn encoding_sizes(encoding: &Encoding) -> Option<(u64, u64)> {
+    match encoding {
+        Encoding::ANSI | Encoding::UTF8 => Some((0, 1)),
+        Encoding::UTF8BOM => Some((3, 1)),
+        Encoding::UTF16LE | Encoding::UTF16BE => Some((2, 2)),
+        Encoding::UNKNOWN(_) => None,
+    }
+}
+
+/// Returns the number of bytes used by `c` on disk
+fn char_size(c: char, encoding: &Encoding) -> u64 {
+    match encoding {
+        Encoding::UTF16LE | Encoding::UTF16BE => c.len_utf16() as u64 * 2,
+        Encoding::UTF8 | Encoding::UTF8BOM => c.len_utf8() as u64,
+        // Single byte code pages
+        _ => 1,
+    }
+}
+
+/// Returns the number of code units used by the line ending on disk
+fn line_ending_units(cr_type: &CRType) -> Option<u64> {
+    match cr_type {
+        CRType::CRLF => Some(2),
+        CRType::CR | CRType::LF => Some(1),
+        CRType::UNKNOWN(_) => None,
+    }
+}
+
+/// Encoding details used to calculate `TextPosition::file_offset`
+#[derive(Clone, Copy)]
+struct Layout<'a> {
+    encoding: Option<&'a Encoding>,
+    /// BOM size and code unit size of `encoding`
+    sizes: Option<(u64, u64)>,
+    /// Code units of a line ending of `cr_type`
+    line_ending: Option<u64>,
+}
+
+impl<'a> Layout<'a> {
+    fn new(encoding: Option<&'a Encoding>, cr_type: Option<&CRType>) -> Self {
+        Self {
+            encoding,
+            sizes: encoding.and_then(encoding_sizes),
+            line_ending: cr_type.and_then(line_ending_units),
+        }
+    }
+}
+
+/// Running totals while walking the characters of the buffer
+#[derive(Clone, Copy)]
+struct Scan {
+    units: u64,
+    chars: u64,
+    utf8: u64,
+    line: u64,
+    column: u64,
+    file_offset: Option<u64>,
+    previous: Option<char>,
+}
+
+impl Scan {
+    /// Totals at the start of the buffer
+    fn start(layout: &Layout) -> Self {
+        Self {
+            units: 0,
+            chars: 0,
+            utf8: 0,
+            line: 1,
+            column: 1,
+            file_offset: layout.sizes.map(|(bom, _)| bom),
+            previous: None,
+        }
+    }
+
+    /// Totals of a part of the buffer to `join` to the totals before it. `line` counts the line
+    /// breaks, `column` is absolute after a line break and relative before it
+    fn part(layout: &Layout, previous: Option<char>) -> Self {
+        Self {
+            units: 0,
+            chars: 0,
+            utf8: 0,
+            line: 0,
+            column: 0,
+            file_offset: layout.sizes.map(|_| 0),
+            previous,
+        }
+    }
+
+    fn step(&mut self, c: char, layout: &Layout) {
+        self.units += c.len_utf16() as u64;
+        self.chars += 1;
+        self.utf8 += c.len_utf8() as u64;
+
+        let is_crlf_tail = c == '\n' && self.previous == Some('\r');
+        self.file_offset = match (self.file_offset, layout.sizes, layout.encoding) {
+            (Some(offset), Some((_, unit_size)), Some(encoding)) => match c {
+                '\r' | '\n' if is_crlf_tail => Some(offset),
+                '\r' | '\n' => layout.line_ending.map(|units| offset + units * unit_size),
+                _ => Some(offset + char_size(c, encoding)),
+            },
+            _ => None,
+        };
+
+        match c {
+            '\n' if is_crlf_tail => {}
+            '\r' | '\n' => {
+                self.line += 1;
+                self.column = 1;
+            }
+            _ => self.column += 1,
+        }
+        self.previous = Some(c);
+    }
+
+    /// Walk `chars` until the character crossing `utf16_offset`
+    fn step_until<I: Iterator<Item = char>>(
+        &mut self,
+        chars: I,
+        utf16_offset: u64,
+        layout: &Layout,
+    ) {
+        for c in chars {
+            // An offset in the middle of a surrogate pair is mapped to the start of the character
+            if self.units + c.len_utf16() as u64 > utf16_offset {
+                break;
+            }
+            self.step(c, layout);
+        }
+    }
+
+    /// Add the totals of the `part` following these totals
+    fn join(&self, part: &Scan) -> Scan {
+        Scan {
+            units: self.units + part.units,
+            chars: self.chars + part.chars,
+            utf8: self.utf8 + part.utf8,
+            line: self.line + part.line,
+            column: match part.line {
+                0 => self.column + part.column,
+                _ => part.column,
+            },
+            file_offset: match (self.file_offset, part.file_offset) {
+                (Some(offset), Some(size)) => Some(offset + size),
+                _ => None,
+            },
+            previous: part.previous.or(self.previous),
+        }
+    }
+
+    fn position(&self, utf16_offset: u64) -> TextPosition {
+        TextPosition {
+            utf16_offset,
+            char_index: self.chars,
+            utf8_offset: self.utf8,
+            line: self.line,
+            column: self.column,
+            file_offset: self.file_offset,
+        }
+    }
+}
+
+/// Map `utf16_offset` in `text` to a `TextPosition`. Line breaks in the buffer (`\r`, `\n` or `\r\n`)
+/// are counted as one line ending of `cr_type` when calculating `file_offset`.
+/// Returns `None` if the offset is outside `text`
+pub fn map_position(
+    text: &str,
+    utf16_offset: u64,
+    encoding: Option<&Encoding>,
+    cr_type: Option<&CRType>,
+) -> Option<TextPosition> {
+    if utf16_offset > text.encode_utf16().count() as u64 {
+        return None;
+    }
+
+    let layout = Layout::new(encoding, cr_type);
+    let mut scan = Scan::start(&layout);
+    scan.step_until(text.chars(), utf16_offset, &layout);
+    Some(scan.position(utf16_offset))
+}
+
+/// Code units in a block of `PositionIndex`
+const BLOCK_UNITS: usize = 1024;
+
+fn is_high_surrogate(unit: u16) -> bool {
+    (0xD800..=0xDBFF).contains(&unit)
+}
+
+fn is_low_surrogate(unit: u16) -> bool {
+    (0xDC00..=0xDFFF).contains(&unit)
+}
+
+/// Characters of UTF-16 `units`, unpaired surrogates are replaced like `String::from_utf16_lossy`
+fn decode(units: &[u16]) -> impl Iterator<Item = char> + '_ {
+    char::decode_utf16(units.iter().copied()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+/// Part of the buffer in `PositionIndex` with its totals
+struct Block {
+    units: Vec<u16>,
+    totals: Scan,
+    /// Totals when the previous character is `\r`, only if the block starts with `\n`
+    totals_after_cr: Option<Scan>,
+}
+
+impl Block {
+    fn new(units: Vec<u16>, layout: &Layout) -> Self {
+        let scan = |previous| {
+            let mut totals = Scan::part(layout, previous);
+            decode(&units).for_each(|c| totals.step(c, layout));
+            totals
+        };
+        let totals = scan(None);
+        let totals_after_cr = match units.first() {
+            Some(0x0A) => Some(scan(Some('\r'))),
+            _ => None,
+        };
+        Self {
+            units,
+            totals,
+            totals_after_cr,
+        }
+    }
+
+    fn totals(&self, previous: Option<char>) -> &Scan {
+        match (previous, &self.totals_after_cr) {
+            (Some('\r'), Some(totals)) => totals,
+            _ => &self.totals,
+        }
+    }
+
+    /// Split `units` in blocks without splitting the surrogate pairs
+    fn split(units: Vec<u16>, layout: &Layout) -> Vec<Block> {
+        let mut blocks = vec![];
+        let mut start = 0;
+        while start < units.len() {
+            let mut end = (start + BLOCK_UNITS).min(units.len());
+            if end < units.len()
+                && is_high_surrogate(units[end - 1])
+                && is_low_surrogate(units[end])
+            {
+                end += 1;
+            }
+            blocks.push(Block::new(units[start..end].to_vec(), layout));
+            start = end;
+        }
+        blocks
+    }
+}
+
+/// Maps offsets to `TextPosition`s while edits are applied to the buffer. The buffer is kept in
+/// blocks with their totals, so mapping an offset or applying an edit only walks the blocks and
+/// scans a few of them instead of the whole buffer
+pub(crate) struct PositionIndex<'a> {
+    blocks: Vec<Block>,
+    len: usize,
+    layout: Layout<'a>,
+}
+
+impl<'a> PositionIndex<'a> {
+    pub(crate) fn new(
+        text: &str,
+        encoding: Option<&'a Encoding>,
+        cr_type: Option<&CRType>,
+    ) -> Self {
+        let layout = Layout::new(encoding, cr_type);
+        let units = text.encode_utf16().collect::<Vec<u16>>();
+        Self {
+            len: units.len(),
+            blocks: Block::split(units, &layout),
+            layout,
+        }
+    }
+
+    /// Same as `map_position` on the current buffer
+    pub(crate) fn position(&self, utf16_offset: u64) -> Option<TextPosition> {
+        if utf16_offset > self.len as u64 {
+            return None;
+        }
+
+        let mut scan = Scan::start(&self.layout);
+        for block in &self.blocks {
+            let totals = block.totals(scan.previous);
+            if scan.units + totals.units <= utf16_offset {
+                scan = scan.join(totals);
+                continue;
+            }
+            scan.step_until(decode(&block.units), utf16_offset, &self.layout);
+            break;
+        }
+        Some(scan.position(utf16_offset))
+    }
+
+    /// Code units in `start..end` of the current buffer, `None` if the range is outside the buffer
+    pub(crate) fn units(&self, start: usize, end: usize) -> Option<Vec<u16>> {
+        if start > end || end > self.len {
+            return None;
+        }
+
+        let mut units = Vec::with_capacity(end - start);
+        let mut offset = 0;
+        for block in &self.blocks {
+            let block_end = offset + block.units.len();
+            if block_end > start && offset < end {
+                let range = start.saturating_sub(offset)..(end - offset).min(block.units.len());
+                units.extend_from_slice(&block.units[range]);
+            }
+            if block_end >= end {
+                break;
+            }
+            offset = block_end;
+        }
+        Some(units)
+    }
+
+    /// The code units of the current buffer
+    pub(crate) fn into_units(self) -> Vec<u16> {
+        self.blocks
+            .into_iter()
+            .flat_map(|block| block.units)
+            .collect()
+    }
+
+    /// Replace `deletion` code units at `position` with `data`. Positions outside the buffer are
+    /// clamped the same way as `UnsavedChunk::apply`
+    pub(crate) fn splice(&mut self, position: usize, deletion: usize, data: &[u16]) {
+        let start = position.min(self.len);
+        let end = start.saturating_add(deletion).min(self.len);
+
+        // Blocks holding `start` and `end`, with a neighbour on each side so a surrogate pair
+        // created by the edit is not split between blocks
+        let mut first = 0;
+        let mut offset = 0;
+        while first + 1 < self.blocks.len() && offset + self.blocks[first].units.len() < start {
+            offset += self.blocks[first].units.len();
+            first += 1;
+        }
+        let mut last = first;
+        let mut last_offset = offset;
+        while last + 1 < self.blocks.len() && last_offset + self.blocks[last].units.len() < end {
+            last_offset += self.blocks[last].units.len();
+            last += 1;
+        }
+        if first > 0 {
+            first -= 1;
+            offset -= self.blocks[first].units.len();
+        }
+        let last = (last + 1).min(self.blocks.len());
+
+        let mut units = self.blocks[first..last]
+            .iter()
+            .flat_map(|block| block.units.iter().copied())
+            .collect::<Vec<u16>>();
+        units.splice(start - offset..end - offset, data.iter().copied());
+        let blocks = Block::split(units, &self.layout);
+        self.blocks.splice(first..last, blocks);
+        self.len = self.len - (end - start) + data.len();
+    }
+}
+
+/// Default number of characters taken from each side of the cursor for the cursor context
+pub const DEFAULT_CONTEXT_SIZE: usize = 32;
+
+/// Text derived from the cursor, computed against the saved content and the reconstructed buffer
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default, JsonSchema)]
+pub struct CursorText {
+    /// Computed against `file_content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved: Option<String>,
+    /// Computed against the buffer after replaying the unsaved chunks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconstructed: Option<String>,
+}
+
+impl CursorText {
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_none() && self.reconstructed.is_none()
+    }
+}
+
+/// Get the text between the UTF-16 offsets `start` and `end` with `context_size` characters
+/// before and after it. Returns `None` if any of the offsets is outside `text`
+pub fn text_range(text: &str, start: u64, end: u64, context_size: usize) -> Option<String> {
+    let (start, end) = (start.min(end), start.max(end));
+    let start = map_position(text, start, None, None)?.char_index as usize;
+    let end = map_position(text, end, None, None)?.char_index as usize;
+    let first = start.saturating_sub(context_size);
+
+    Some(
+        text.chars()
+            .skip(first)
+            .take(end.saturating_add(context_size).saturating_sub(first))
+            .collect(),
+    )
+}
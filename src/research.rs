@@ -0,0 +1,127 @@
+use crate::NotepadTabStat;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+};
+use winparsingtools::utils::bytes_to_hex;
+
+/// A field that is read from the `TabState` file but its meaning is still unknown
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct UndecodedField {
+    /// Name of the field as used in the parser (e.g. `ConfigBlock::unknown0`)
+    pub field: String,
+    /// Offset of the first byte of the field from the start of the `TabState` file
+    pub offset: u64,
+    /// Raw bytes as hex
+    pub value: String,
+}
+
+impl UndecodedField {
+    pub fn new(field: &str, offset: u64, value: &[u8]) -> Self {
+        Self {
+            field: field.to_string(),
+            offset,
+            value: bytes_to_hex(&value.to_vec()),
+        }
+    }
+}
+
+/// A parsed record along with all of its undecoded fields. Used for the research output mode
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ResearchRecord<'a> {
+    #[serde(flatten)]
+    pub record: &'a NotepadTabStat,
+    pub undecoded_fields: &'a [UndecodedField],
+}
+
+impl<'a> From<&'a NotepadTabStat> for ResearchRecord<'a> {
+    fn from(value: &'a NotepadTabStat) -> Self {
+        Self {
+            record: value,
+            undecoded_fields: &value.undecoded_fields,
+        }
+    }
+}
+
+/// Frequency table of the values observed in the undecoded fields across multiple files
+#[derive(Debug, Serialize, Default)]
+pub struct UndecodedFieldsStats {
+    /// Number of records added to the table
+    pub files: u64,
+    /// Field name -> observed value (hex) -> number of occurrences
+    pub fields: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl UndecodedFieldsStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the undecoded values of `record`
+    pub fn add(&mut self, record: &NotepadTabStat) {
+        self.files += 1;
+        for field in &record.undecoded_fields {
+            *self
+                .fields
+                .entry(field.field.clone())
+                .or_default()
+                .entry(field.value.clone())
+                .or_insert(0) += 1;
+        }
+    }
+}
+
+/// Wraps a reader and keeps track of the number of bytes read so far
+pub(crate) struct PositionReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner, position: 0 }
+    }
+
+    /// Number of bytes read from the inner reader
+    pub(crate) fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.position += size as u64;
+        Ok(size)
+    }
+}
+
+/// Wraps a reader and keeps the bytes read through it
+pub(crate) struct RecordingReader<'a, R> {
+    inner: &'a mut R,
+    bytes: Vec<u8>,
+}
+
+impl<'a, R: Read> RecordingReader<'a, R> {
+    pub(crate) fn new(inner: &'a mut R) -> Self {
+        Self {
+            inner,
+            bytes: vec![],
+        }
+    }
+
+    /// The bytes read from the inner reader
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<R: Read> Read for RecordingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
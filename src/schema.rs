@@ -0,0 +1,104 @@
+use crate::{
+    output::{ChunkCsvRecord, CsvRecord, SummaryCsvRecord},
+    research::ResearchRecord,
+    summary::TabStateSummary,
+    NotepadTabStat,
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, RootSchema, Schema, SchemaObject},
+    schema_for, JsonSchema,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Version of the output records schema. The major version changes when fields are removed,
+/// renamed or change their type, the minor version changes when fields are added
+pub const SCHEMA_VERSION: &str = "1.2";
+
+/// Serialized as `SCHEMA_VERSION` in every output record. Deserialized records are serialized
+/// again with the current schema, so only records with the same major version are accepted
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(SCHEMA_VERSION)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let version = String::deserialize(deserializer)?;
+        if major_version(&version) != major_version(SCHEMA_VERSION) {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported schema version '{}', expected a {}.x version",
+                version,
+                major_version(SCHEMA_VERSION).unwrap_or_default()
+            )));
+        }
+        Ok(SchemaVersion)
+    }
+}
+
+/// The major part of a `major.minor` version
+fn major_version(version: &str) -> Option<u64> {
+    let (major, minor) = version.split_once('.')?;
+    minor.parse::<u64>().ok()?;
+    major.parse().ok()
+}
+
+impl JsonSchema for SchemaVersion {
+    fn schema_name() -> String {
+        "SchemaVersion".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        Schema::Object(SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            const_value: Some(SCHEMA_VERSION.into()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Schema of an optional `FileTime`, serialized as an RFC 3339 timestamp in UTC
+pub fn filetime_schema(_: &mut SchemaGenerator) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(vec![InstanceType::String, InstanceType::Null].into()),
+        format: Some("date-time".to_string()),
+        ..Default::default()
+    })
+}
+
+/// `schema_version` defaults when reading records written before it was added, but it is always written
+fn require_schema_version(mut schema: RootSchema) -> RootSchema {
+    if let Some(object) = schema.schema.object.as_mut() {
+        object.required.insert("schema_version".to_string());
+    }
+    schema
+}
+
+/// JSON Schema of each record type, keyed by the record type name
+pub fn record_schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("tab", require_schema_version(schema_for!(NotepadTabStat))),
+        (
+            "research",
+            require_schema_version(schema_for!(ResearchRecord)),
+        ),
+        (
+            "summary",
+            require_schema_version(schema_for!(TabStateSummary)),
+        ),
+        ("csv", schema_for!(CsvRecord)),
+        ("csv-chunks", schema_for!(ChunkCsvRecord)),
+        ("summary-csv", schema_for!(SummaryCsvRecord)),
+    ])
+}
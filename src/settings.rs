@@ -0,0 +1,467 @@
+use crate::errors::NotepadErrors;
+use byteorder::{ByteOrder, LittleEndian};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use winparsingtools::{date_time::FileTime, utils::bytes_to_hex};
+
+/// Offset of the first hive bin. All cell offsets are relative to it
+const HIVE_BINS_OFFSET: usize = 0x1000;
+/// Maximum depth of nested keys to walk. Protects the stack against deep corrupted hives
+const MAX_KEY_DEPTH: usize = 32;
+/// `KEY_COMP_NAME` flag, the key name is stored as ASCII instead of UTF-16LE
+const KEY_COMP_NAME: u16 = 0x0020;
+/// `VALUE_COMP_NAME` flag, the value name is stored as ASCII instead of UTF-16LE
+const VALUE_COMP_NAME: u16 = 0x0001;
+/// Packaged apps settings types are `Windows.Foundation.PropertyType` + this base
+const APP_DATA_TYPE_BASE: u32 = 0x5F5E100;
+
+/// Decoded data of an app settings value
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum AppSettingData {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    /// Data that isn't decoded, as hex
+    Raw(String),
+}
+
+/// A single value from the `settings.dat` hive
+#[derive(Debug, Serialize)]
+pub struct AppSettingValue {
+    /// Path of the key containing the value, relative to the hive root (e.g. `LocalState`)
+    pub key: String,
+    pub name: String,
+    pub data_type: String,
+    pub data: AppSettingData,
+    /// Packaged apps append the last write time of the value to its data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<FileTime>,
+}
+
+/// Notepad relevant application settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+pub struct NotepadSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<AppSettingData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<AppSettingData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_style: Option<AppSettingData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<AppSettingData>,
+    /// Startup behaviour, open files in a new tab or a new window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_file: Option<AppSettingData>,
+    /// Continue the previous session on startup. When disabled the `TabState` files of closed tabs are deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_restore: Option<AppSettingData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word_wrap: Option<AppSettingData>,
+}
+
+/// Parsed Notepad `settings.dat` registry hive
+#[derive(Debug, Serialize)]
+pub struct AppSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings_path: Option<String>,
+    pub values: Vec<AppSettingValue>,
+}
+
+impl AppSettings {
+    /// Read the file from `path` and use `from_reader` to parse it
+    pub fn from_path(path: &str) -> std::result::Result<Self, NotepadErrors> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(NotepadErrors::FileOpen(e.to_string(), path.to_string())),
+        };
+
+        let mut parsed = Self::from_reader(&mut file)?;
+        parsed.settings_path = Some(path.to_string());
+
+        Ok(parsed)
+    }
+
+    /// Parse the hive from reader. Transaction logs (`settings.dat.LOG1`/`LOG2`) aren't applied
+    pub fn from_reader<R: Read>(reader: &mut R) -> std::result::Result<Self, NotepadErrors> {
+        let mut data = vec![];
+        if let Err(e) = reader.read_to_end(&mut data) {
+            return Err(NotepadErrors::ReadError(
+                e.to_string(),
+                "AppSettings".to_string(),
+            ));
+        }
+
+        if data.len() < HIVE_BINS_OFFSET || &data[0..4] != b"regf" {
+            return Err(NotepadErrors::UnexpectedValue(
+                "regf".to_string(),
+                String::from_utf8_lossy(&data[0..data.len().min(4)]).to_string(),
+                "AppSettings::signature".to_string(),
+            ));
+        }
+
+        let hive = Hive {
+            data: &data,
+            visited: RefCell::default(),
+        };
+        let root_offset = LittleEndian::read_u32(&data[0x24..0x28]);
+        let mut values = vec![];
+        hive.read_key(root_offset, "", 0, &mut values)?;
+
+        Ok(Self {
+            settings_path: None,
+            values,
+        })
+    }
+
+    /// Get the value `name` from the key `key`
+    pub fn value(&self, key: &str, name: &str) -> Option<&AppSettingValue> {
+        self.values
+            .iter()
+            .find(|value| value.key.eq_ignore_ascii_case(key) && value.name == name)
+    }
+
+    /// Extract the Notepad relevant settings from the `LocalState` key. The value names were observed
+    /// in `settings.dat` files, values not listed here are still available in `values`
+    pub fn notepad_settings(&self) -> NotepadSettings {
+        let get = |name: &str| self.value("LocalState", name).map(|v| v.data.clone());
+        NotepadSettings {
+            font_family: get("FontFamily"),
+            font_size: get("FontSize"),
+            font_style: get("FontStyle"),
+            theme: get("Theme"),
+            open_file: get("OpenFile"),
+            session_restore: get("GhostFile"),
+            word_wrap: get("WordWrap"),
+        }
+    }
+}
+
+/// Path of `settings.dat` for the Notepad package containing the `TabState` file `tabstate_path`.
+/// `TabState` files are in `<package>\LocalState\TabState` and the hive is in `<package>\Settings`
+pub fn settings_path_for_tabstate(tabstate_path: &str) -> Option<PathBuf> {
+    let package_dir = Path::new(tabstate_path).parent()?.parent()?.parent()?;
+    Some(package_dir.join("Settings").join("settings.dat"))
+}
+
+struct Hive<'a> {
+    data: &'a [u8],
+    /// Offsets of the key and subkeys list cells already read. Each is referenced once in a
+    /// valid hive, corrupted hives can reference them in a loop or many times
+    visited: RefCell<HashSet<u32>>,
+}
+
+impl<'a> Hive<'a> {
+    /// Mark the cell at `offset` as read, fails if it was already read
+    fn visit(&self, offset: u32, field: &str) -> std::result::Result<(), NotepadErrors> {
+        match self.visited.borrow_mut().insert(offset) {
+            true => Ok(()),
+            false => Err(NotepadErrors::ReadErrorWithSize(
+                "Cell is referenced more than once".to_string(),
+                field.to_string(),
+                offset.to_string(),
+            )),
+        }
+    }
+
+    /// Get the data of the cell at `offset`
+    fn cell(&self, offset: u32, field: &str) -> std::result::Result<&'a [u8], NotepadErrors> {
+        let start = HIVE_BINS_OFFSET + offset as usize;
+        if start + 4 > self.data.len() {
+            return Err(NotepadErrors::ReadErrorWithSize(
+                "Cell offset is outside the hive".to_string(),
+                field.to_string(),
+                offset.to_string(),
+            ));
+        }
+        let size = LittleEndian::read_i32(&self.data[start..start + 4]).unsigned_abs() as usize;
+        let end = start + size;
+        if size < 4 || end > self.data.len() {
+            return Err(NotepadErrors::ReadErrorWithSize(
+                "Cell size is outside the hive".to_string(),
+                field.to_string(),
+                size.to_string(),
+            ));
+        }
+        Ok(&self.data[start + 4..end])
+    }
+
+    fn read_key(
+        &self,
+        offset: u32,
+        parent: &str,
+        depth: usize,
+        values: &mut Vec<AppSettingValue>,
+    ) -> std::result::Result<(), NotepadErrors> {
+        if depth > MAX_KEY_DEPTH {
+            return Err(NotepadErrors::UnexpectedValue(
+                format!("depth <= {}", MAX_KEY_DEPTH),
+                depth.to_string(),
+                "AppSettings::key".to_string(),
+            ));
+        }
+
+        self.visit(offset, "AppSettings::nk")?;
+        let nk = self.cell(offset, "AppSettings::nk")?;
+        if nk.len() < 0x4C || &nk[0..2] != b"nk" {
+            return Err(NotepadErrors::UnexpectedValue(
+                "nk".to_string(),
+                String::from_utf8_lossy(&nk[0..nk.len().min(2)]).to_string(),
+                "AppSettings::nk".to_string(),
+            ));
+        }
+
+        let flags = LittleEndian::read_u16(&nk[0x02..0x04]);
+        let subkeys_count = LittleEndian::read_u32(&nk[0x14..0x18]);
+        let subkeys_offset = LittleEndian::read_u32(&nk[0x1C..0x20]);
+        let values_count = LittleEndian::read_u32(&nk[0x24..0x28]);
+        let values_offset = LittleEndian::read_u32(&nk[0x28..0x2C]);
+        let name_size = LittleEndian::read_u16(&nk[0x48..0x4A]) as usize;
+        let name = match nk.get(0x4C..0x4C + name_size) {
+            Some(name) => decode_name(name, flags & KEY_COMP_NAME != 0),
+            None => {
+                return Err(NotepadErrors::ReadErrorWithSize(
+                    "Key name is outside the cell".to_string(),
+                    "AppSettings::nk::name".to_string(),
+                    name_size.to_string(),
+                ))
+            }
+        };
+
+        // The root key name isn't part of the path
+        let path = match depth {
+            0 => String::new(),
+            1 => name,
+            _ => format!("{}\\{}", parent, name),
+        };
+
+        if values_count > 0 {
+            let list = self.cell(values_offset, "AppSettings::values_list")?;
+            for i in 0..values_count as usize {
+                let Some(entry) = list.get(i * 4..i * 4 + 4) else {
+                    break;
+                };
+                values.push(self.read_value(LittleEndian::read_u32(entry), &path)?);
+            }
+        }
+
+        if subkeys_count > 0 {
+            for subkey in self.read_subkeys_list(subkeys_offset, 0)? {
+                self.read_key(subkey, &path, depth + 1, values)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the offsets of the subkeys from `lf`, `lh`, `li` and `ri` lists
+    fn read_subkeys_list(
+        &self,
+        offset: u32,
+        depth: usize,
+    ) -> std::result::Result<Vec<u32>, NotepadErrors> {
+        self.visit(offset, "AppSettings::subkeys_list")?;
+        let list = self.cell(offset, "AppSettings::subkeys_list")?;
+        if list.len() < 4 || depth > MAX_KEY_DEPTH {
+            return Err(NotepadErrors::ReadError(
+                "Invalid subkeys list".to_string(),
+                "AppSettings::subkeys_list".to_string(),
+            ));
+        }
+        let count = LittleEndian::read_u16(&list[2..4]) as usize;
+        let entry_size = match &list[0..2] {
+            b"lf" | b"lh" => 8,
+            b"li" | b"ri" => 4,
+            x => {
+                return Err(NotepadErrors::UnexpectedValue(
+                    "lf|lh|li|ri".to_string(),
+                    String::from_utf8_lossy(x).to_string(),
+                    "AppSettings::subkeys_list".to_string(),
+                ))
+            }
+        };
+
+        let mut offsets = vec![];
+        for i in 0..count {
+            let start = 4 + i * entry_size;
+            let Some(entry) = list.get(start..start + 4) else {
+                break;
+            };
+            let entry = LittleEndian::read_u32(entry);
+            match &list[0..2] {
+                b"ri" => offsets.extend(self.read_subkeys_list(entry, depth + 1)?),
+                _ => offsets.push(entry),
+            }
+        }
+
+        Ok(offsets)
+    }
+
+    fn read_value(
+        &self,
+        offset: u32,
+        key: &str,
+    ) -> std::result::Result<AppSettingValue, NotepadErrors> {
+        let vk = self.cell(offset, "AppSettings::vk")?;
+        if vk.len() < 0x14 || &vk[0..2] != b"vk" {
+            return Err(NotepadErrors::UnexpectedValue(
+                "vk".to_string(),
+                String::from_utf8_lossy(&vk[0..vk.len().min(2)]).to_string(),
+                "AppSettings::vk".to_string(),
+            ));
+        }
+
+        let name_size = LittleEndian::read_u16(&vk[0x02..0x04]) as usize;
+        let data_size = LittleEndian::read_u32(&vk[0x04..0x08]);
+        let data_offset = LittleEndian::read_u32(&vk[0x08..0x0C]);
+        let data_type = LittleEndian::read_u32(&vk[0x0C..0x10]);
+        let flags = LittleEndian::read_u16(&vk[0x10..0x12]);
+        let name = match vk.get(0x14..0x14 + name_size) {
+            Some(name) => decode_name(name, flags & VALUE_COMP_NAME != 0),
+            None => {
+                return Err(NotepadErrors::ReadErrorWithSize(
+                    "Value name is outside the cell".to_string(),
+                    "AppSettings::vk::name".to_string(),
+                    name_size.to_string(),
+                ))
+            }
+        };
+
+        // If the most significant bit is set, the data is stored in the offset field
+        let raw = if data_size & 0x8000_0000 != 0 {
+            let size = (data_size & 0x7FFF_FFFF).min(4) as usize;
+            vk[0x08..0x08 + size].to_vec()
+        } else {
+            let cell = self.cell(data_offset, "AppSettings::vk::data")?;
+            match cell.get(0..data_size as usize) {
+                Some(raw) if &raw[0..raw.len().min(2)] != b"db" => raw.to_vec(),
+                _ => {
+                    return Err(NotepadErrors::ReadErrorWithSize(
+                        "Unsupported value data".to_string(),
+                        format!("AppSettings::vk::{}", name),
+                        data_size.to_string(),
+                    ))
+                }
+            }
+        };
+
+        let (data_type, data, timestamp) = decode_data(data_type, &raw);
+
+        Ok(AppSettingValue {
+            key: key.to_string(),
+            name,
+            data_type,
+            data,
+            timestamp,
+        })
+    }
+}
+
+fn decode_name(name: &[u8], compressed: bool) -> String {
+    match compressed {
+        true => name.iter().map(|&c| c as char).collect(),
+        false => decode_utf16(name),
+    }
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units = data
+        .chunks_exact(2)
+        .map(LittleEndian::read_u16)
+        .take_while(|&c| c != 0)
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+/// Decode the value data based on its type. Returns the type name, the data and the timestamp for packaged apps types
+fn decode_data(data_type: u32, raw: &[u8]) -> (String, AppSettingData, Option<FileTime>) {
+    let hex = AppSettingData::Raw(bytes_to_hex(&raw.to_vec()));
+
+    // Standard registry types
+    if data_type < APP_DATA_TYPE_BASE {
+        return match data_type {
+            0x01 | 0x02 => (
+                "REG_SZ".to_string(),
+                AppSettingData::String(decode_utf16(raw)),
+                None,
+            ),
+            0x04 if raw.len() >= 4 => (
+                "REG_DWORD".to_string(),
+                AppSettingData::UInt(LittleEndian::read_u32(raw) as u64),
+                None,
+            ),
+            0x0B if raw.len() >= 8 => (
+                "REG_QWORD".to_string(),
+                AppSettingData::UInt(LittleEndian::read_u64(raw)),
+                None,
+            ),
+            x => (format!("REG_{:#X}", x), hex, None),
+        };
+    }
+
+    // Packaged apps types, the last 8 bytes are a FILETIME
+    if raw.len() < 8 {
+        return (format!("{:#X}", data_type), hex, None);
+    }
+    let (value, timestamp) = raw.split_at(raw.len() - 8);
+    let timestamp = Some(FileTime::new(LittleEndian::read_u64(timestamp)));
+    let sized = |size: usize| value.len() >= size;
+
+    let (name, data) = match data_type - APP_DATA_TYPE_BASE {
+        0x01 if sized(1) => ("UInt8", AppSettingData::UInt(value[0] as u64)),
+        0x02 if sized(2) => (
+            "Int16",
+            AppSettingData::Int(LittleEndian::read_i16(value) as i64),
+        ),
+        0x03 if sized(2) => (
+            "UInt16",
+            AppSettingData::UInt(LittleEndian::read_u16(value) as u64),
+        ),
+        0x04 if sized(4) => (
+            "Int32",
+            AppSettingData::Int(LittleEndian::read_i32(value) as i64),
+        ),
+        0x05 if sized(4) => (
+            "UInt32",
+            AppSettingData::UInt(LittleEndian::read_u32(value) as u64),
+        ),
+        0x06 if sized(8) => ("Int64", AppSettingData::Int(LittleEndian::read_i64(value))),
+        0x07 if sized(8) => (
+            "UInt64",
+            AppSettingData::UInt(LittleEndian::read_u64(value)),
+        ),
+        0x08 if sized(4) => (
+            "Single",
+            AppSettingData::Float(LittleEndian::read_f32(value) as f64),
+        ),
+        0x09 if sized(8) => (
+            "Double",
+            AppSettingData::Float(LittleEndian::read_f64(value)),
+        ),
+        0x0A => ("Char16", AppSettingData::String(decode_utf16(value))),
+        0x0B if sized(1) => ("Boolean", AppSettingData::Bool(value[0] != 0)),
+        0x0C => ("String", AppSettingData::String(decode_utf16(value))),
+        0x0E if sized(8) => (
+            "DateTime",
+            AppSettingData::String(FileTime::new(LittleEndian::read_u64(value)).to_string()),
+        ),
+        x => {
+            return (
+                format!("{:#X}", x + APP_DATA_TYPE_BASE),
+                AppSettingData::Raw(bytes_to_hex(&value.to_vec())),
+                timestamp,
+            )
+        }
+    };
+
+    (name.to_string(), data, timestamp)
+}
@@ -0,0 +1,456 @@
+use crate::{
+    errors::NotepadErrors,
+    output::{enum_text, OutputSink, SinkOptions},
+    research::ResearchRecord,
+    schema::SCHEMA_VERSION,
+    summary::TabStateSummary,
+    timestamps::filetime_text,
+    NotepadTabStat,
+};
+use rusqlite::{params, Connection};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use winparsingtools::date_time::FileTime;
+
+/// Tables of the database. `search` is a full-text index over the paths, the saved content, the
+/// reconstructed content and the text added and deleted by the edit sessions. `source` is the
+/// field the text is from and `chunk_index` the first chunk of the edit session.
+/// `tabs.raw` is the JSON record with the undecoded fields, only written with `research`
+const SCHEMA: &str = "
+CREATE TABLE files (
+    id INTEGER PRIMARY KEY,
+    schema_version TEXT NOT NULL,
+    tabstate_path TEXT,
+    tab_guid TEXT,
+    tabstate_modified TEXT,
+    tabstate_accessed TEXT,
+    tabstate_changed TEXT,
+    tabstate_created TEXT,
+    tabstate_size INTEGER
+);
+CREATE TABLE tabs (
+    id INTEGER PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES files(id),
+    notepad_version TEXT,
+    seq_number INTEGER NOT NULL,
+    is_saved_file INTEGER NOT NULL,
+    path_size INTEGER NOT NULL,
+    path TEXT,
+    file_size INTEGER,
+    encoding TEXT,
+    cr_type TEXT,
+    last_write_time TEXT,
+    file_hash TEXT,
+    cursor_start INTEGER,
+    cursor_end INTEGER,
+    cursor_start_char_index INTEGER,
+    cursor_start_utf8_offset INTEGER,
+    cursor_start_line INTEGER,
+    cursor_start_column INTEGER,
+    cursor_start_file_offset INTEGER,
+    cursor_end_char_index INTEGER,
+    cursor_end_utf8_offset INTEGER,
+    cursor_end_line INTEGER,
+    cursor_end_column INTEGER,
+    cursor_end_file_offset INTEGER,
+    file_content_size INTEGER NOT NULL,
+    file_content TEXT NOT NULL,
+    contain_unsaved_data INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    warnings TEXT,
+    raw TEXT
+);
+CREATE TABLE config (
+    tab_id INTEGER PRIMARY KEY REFERENCES tabs(id),
+    word_wrap INTEGER NOT NULL,
+    rtl INTEGER NOT NULL,
+    show_unicode INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    unknown0 INTEGER NOT NULL,
+    unknown1 INTEGER NOT NULL
+);
+CREATE TABLE chunks (
+    id INTEGER PRIMARY KEY,
+    tab_id INTEGER NOT NULL REFERENCES tabs(id),
+    chunk_index INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    num_of_deletion INTEGER NOT NULL,
+    num_of_addition INTEGER NOT NULL,
+    data TEXT,
+    checksum TEXT NOT NULL,
+    checksum_valid INTEGER,
+    location TEXT
+);
+CREATE TABLE reconstructed (
+    tab_id INTEGER PRIMARY KEY REFERENCES tabs(id),
+    content TEXT NOT NULL
+);
+CREATE TABLE summaries (
+    id INTEGER PRIMARY KEY,
+    schema_version TEXT NOT NULL,
+    tabstate_dir TEXT NOT NULL,
+    tabs_count INTEGER NOT NULL,
+    saved_tabs INTEGER NOT NULL,
+    unsaved_tabs INTEGER NOT NULL,
+    last_activity TEXT,
+    raw TEXT NOT NULL
+);
+CREATE TABLE errors (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    error TEXT NOT NULL
+);
+CREATE INDEX tabs_file_id ON tabs(file_id);
+CREATE INDEX chunks_tab_id ON chunks(tab_id);
+CREATE VIRTUAL TABLE search USING fts5(
+    text,
+    source UNINDEXED,
+    tab_id UNINDEXED,
+    chunk_index UNINDEXED
+);
+";
+
+fn sqlite_error(e: impl ToString, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// RFC 3339 with the fraction of the second so the times sort in order
+fn time_text(time: Option<&FileTime>) -> Option<String> {
+    time.map(filetime_text)
+}
+
+/// Normalized tables in a SQLite database, written to `SinkOptions::output_path`. SQLite can't
+/// write to a stream so without an output path the database is built in a temporary file that
+/// is copied to the output by `finish`
+pub struct SqliteSink {
+    /// The stream the temporary database is copied to, `None` when writing to the output path
+    output: Option<Box<dyn Write>>,
+    path: PathBuf,
+    connection: Option<Connection>,
+    research: bool,
+}
+
+impl SqliteSink {
+    pub fn new(output: Box<dyn Write>, options: &SinkOptions) -> Self {
+        let (output, path) = match &options.output_path {
+            Some(path) => (None, path.clone()),
+            None => {
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_nanos())
+                    .unwrap_or_default();
+                let path = env::temp_dir().join(format!(
+                    "notepad_parser_{}_{}.sqlite",
+                    process::id(),
+                    nanos
+                ));
+                (Some(output), path)
+            }
+        };
+        Self {
+            output,
+            path,
+            connection: None,
+            research: options.research,
+        }
+    }
+
+    fn connection(&self) -> std::result::Result<&Connection, NotepadErrors> {
+        match &self.connection {
+            Some(connection) => Ok(connection),
+            None => Err(sqlite_error(
+                "The database isn't open",
+                "`begin` must be called before writing",
+            )),
+        }
+    }
+
+    fn insert_search(
+        connection: &Connection,
+        text: &str,
+        source: &str,
+        tab_id: i64,
+        chunk_index: Option<usize>,
+    ) -> rusqlite::Result<usize> {
+        connection.execute(
+            "INSERT INTO search (text, source, tab_id, chunk_index) VALUES (?1, ?2, ?3, ?4)",
+            params![text, source, tab_id, chunk_index],
+        )
+    }
+
+    fn insert_record(
+        connection: &Connection,
+        record: &NotepadTabStat,
+        raw: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        let times = record.tabstate_times.as_ref();
+        connection.execute(
+            "INSERT INTO files (schema_version, tabstate_path, tab_guid, tabstate_modified,
+                tabstate_accessed, tabstate_changed, tabstate_created, tabstate_size)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                SCHEMA_VERSION,
+                record.tabstate_path,
+                record.tab_guid(),
+                time_text(times.and_then(|times| times.modified.as_ref())),
+                time_text(times.and_then(|times| times.accessed.as_ref())),
+                time_text(times.and_then(|times| times.changed.as_ref())),
+                time_text(times.and_then(|times| times.created.as_ref())),
+                record.tabstate_size,
+            ],
+        )?;
+        let file_id = connection.last_insert_rowid();
+
+        let warnings = match &record.warnings {
+            Some(warnings) => serde_json::to_string(warnings).ok(),
+            None => None,
+        };
+        // The UTF-16 offsets of the cursor locations are `cursor_start` and `cursor_end`
+        let (start, end) = (
+            record.cursor_start_location.as_ref(),
+            record.cursor_end_location.as_ref(),
+        );
+        connection.execute(
+            "INSERT INTO tabs (file_id, notepad_version, seq_number, is_saved_file, path_size,
+                path, file_size, encoding, cr_type, last_write_time, file_hash, cursor_start,
+                cursor_end, cursor_start_char_index, cursor_start_utf8_offset, cursor_start_line,
+                cursor_start_column, cursor_start_file_offset, cursor_end_char_index,
+                cursor_end_utf8_offset, cursor_end_line, cursor_end_column,
+                cursor_end_file_offset, file_content_size, file_content, contain_unsaved_data,
+                checksum, warnings, raw)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29)",
+            params![
+                file_id,
+                record.notepad_version,
+                record.seq_number,
+                record.is_saved_file,
+                record.path_size,
+                record.path,
+                record.file_size,
+                enum_text(record.encoding.as_ref()),
+                enum_text(record.cr_type.as_ref()),
+                time_text(record.last_write_time.as_ref()),
+                record.file_hash,
+                record.cursor_start,
+                record.cursor_end,
+                start.map(|location| location.char_index),
+                start.map(|location| location.utf8_offset),
+                start.map(|location| location.line),
+                start.map(|location| location.column),
+                start.and_then(|location| location.file_offset),
+                end.map(|location| location.char_index),
+                end.map(|location| location.utf8_offset),
+                end.map(|location| location.line),
+                end.map(|location| location.column),
+                end.and_then(|location| location.file_offset),
+                record.file_content_size,
+                record.file_content,
+                record.contain_unsaved_data,
+                record.checksum,
+                warnings,
+                raw,
+            ],
+        )?;
+        let tab_id = connection.last_insert_rowid();
+
+        let config = &record.config_block;
+        connection.execute(
+            "INSERT INTO config (tab_id, word_wrap, rtl, show_unicode, version, unknown0, unknown1)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                tab_id,
+                config.word_wrap,
+                config.rtl,
+                config.show_unicode,
+                config.version,
+                config.unknown0,
+                config.unknown1,
+            ],
+        )?;
+
+        for (source, path) in [
+            ("tabstate_path", &record.tabstate_path),
+            ("path", &record.path),
+        ] {
+            if let Some(path) = path {
+                Self::insert_search(connection, path, source, tab_id, None)?;
+            }
+        }
+        if !record.file_content.is_empty() {
+            Self::insert_search(
+                connection,
+                &record.file_content,
+                "file_content",
+                tab_id,
+                None,
+            )?;
+        }
+
+        let chunks = record
+            .unsaved_chunks
+            .iter()
+            .flat_map(|chunks| chunks.iter());
+        for (index, chunk) in chunks.enumerate() {
+            connection.execute(
+                "INSERT INTO chunks (tab_id, chunk_index, position, num_of_deletion,
+                    num_of_addition, data, checksum, checksum_valid, location)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    tab_id,
+                    index,
+                    chunk.position(),
+                    chunk.num_of_deletion(),
+                    chunk.num_of_addition(),
+                    chunk.data(),
+                    chunk.checksum(),
+                    chunk.checksum_valid(),
+                    chunk.location().map(|location| location.to_string()),
+                ],
+            )?;
+        }
+        // Most chunks are a single typed character, the edit sessions merge them into words
+        for session in record.edit_sessions.iter().flatten() {
+            for (source, text) in [
+                ("edit_text", &session.text),
+                ("edit_deleted_text", &session.deleted_text),
+            ] {
+                if let Some(text) = text.as_deref().filter(|text| !text.is_empty()) {
+                    Self::insert_search(
+                        connection,
+                        text,
+                        source,
+                        tab_id,
+                        Some(session.first_chunk),
+                    )?;
+                }
+            }
+        }
+
+        if let Some(content) = record.reconstructed_content() {
+            connection.execute(
+                "INSERT INTO reconstructed (tab_id, content) VALUES (?1, ?2)",
+                params![tab_id, content],
+            )?;
+            Self::insert_search(connection, content, "reconstructed", tab_id, None)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl OutputSink for SqliteSink {
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        // Replace the database if the output file already has one
+        fs::File::create(&self.path).map_err(|e| {
+            NotepadErrors::FileOpen(e.to_string(), self.path.to_string_lossy().to_string())
+        })?;
+        let connection = Connection::open(&self.path)
+            .map_err(|e| sqlite_error(e, "Unable to create the database"))?;
+        // The database is only read after `finish`, no need for a journal
+        connection
+            .execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .and_then(|_| connection.execute_batch("BEGIN"))
+            .map_err(|e| sqlite_error(e, "Unable to create the tables"))?;
+        self.connection = Some(connection);
+        Ok(())
+    }
+
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        let raw = match self.research {
+            true => Some(
+                serde_json::to_string(&ResearchRecord::from(record))
+                    .map_err(|e| sqlite_error(e, "Unable to convert to JSON"))?,
+            ),
+            false => None,
+        };
+
+        let connection = self.connection()?;
+        // Keep the tables consistent if an insert fails
+        connection
+            .execute_batch("SAVEPOINT record")
+            .and_then(|_| Self::insert_record(connection, record, raw.as_deref()))
+            .and_then(|_| connection.execute_batch("RELEASE record"))
+            .map_err(|e| {
+                let _ = connection.execute_batch("ROLLBACK TO record; RELEASE record");
+                sqlite_error(e, "Unable to insert the record")
+            })
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        let raw = serde_json::to_string(summary)
+            .map_err(|e| sqlite_error(e, "Unable to convert to JSON"))?;
+        self.connection()?
+            .execute(
+                "INSERT INTO summaries (schema_version, tabstate_dir, tabs_count, saved_tabs,
+                    unsaved_tabs, last_activity, raw)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    SCHEMA_VERSION,
+                    summary.tabstate_dir,
+                    summary.tabs_count,
+                    summary.saved_tabs,
+                    summary.unsaved_tabs,
+                    time_text(summary.last_activity.map(FileTime::from).as_ref()),
+                    raw,
+                ],
+            )
+            .map(|_| ())
+            .map_err(|e| sqlite_error(e, "Unable to insert the summary"))
+    }
+
+    fn error(
+        &mut self,
+        path: &str,
+        error: &NotepadErrors,
+    ) -> std::result::Result<(), NotepadErrors> {
+        self.connection()?
+            .execute(
+                "INSERT INTO errors (path, error) VALUES (?1, ?2)",
+                params![path, error.to_string()],
+            )
+            .map(|_| ())
+            .map_err(|e| sqlite_error(e, "Unable to insert the error"))
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        let connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => return Ok(()),
+        };
+        connection
+            .execute_batch("COMMIT")
+            .map_err(|e| sqlite_error(e, "Unable to commit the database"))?;
+        connection
+            .close()
+            .map_err(|(_, e)| sqlite_error(e, "Unable to close the database"))?;
+
+        let Some(output) = self.output.as_mut() else {
+            return Ok(());
+        };
+        let mut database = fs::File::open(&self.path).map_err(|e| {
+            NotepadErrors::FileOpen(e.to_string(), self.path.to_string_lossy().to_string())
+        })?;
+        io::copy(&mut database, output)
+            .and_then(|_| output.flush())
+            .map_err(|e| sqlite_error(e, "Unable to copy the database to the output"))?;
+        let _ = fs::remove_file(&self.path);
+        Ok(())
+    }
+}
+
+impl Drop for SqliteSink {
+    fn drop(&mut self) {
+        self.connection.take();
+        // Only the temporary database is removed
+        if self.output.is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{errors::NotepadErrors, schema::SchemaVersion, NotepadTabStat};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fs;
+use winparsingtools::date_time::FileTime;
+
+/// Returns `true` if the file name is `<GUID>.bin`, which is the format used for the tabs `TabState` files
+fn is_tab_file_name(name: &str) -> bool {
+    match name.strip_suffix(".bin") {
+        Some(guid) => {
+            guid.len() == 36
+                && guid.char_indices().all(|(i, c)| match i {
+                    8 | 13 | 18 | 23 => c == '-',
+                    _ => c.is_ascii_hexdigit(),
+                })
+        }
+        None => false,
+    }
+}
+
+/// A single tab inside a `TabStateSummary`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SummaryTab {
+    pub tab_guid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tabstate_path: Option<String>,
+    pub seq_number: u64,
+    pub is_saved_file: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::timestamps::serialize_filetime"
+    )]
+    pub last_write_time: Option<FileTime>,
+    pub contain_unsaved_data: bool,
+    pub has_unsaved_chunks: bool,
+    /// Last modification time of the `TabState` file
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::timestamps::serialize_datetime"
+    )]
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl From<&NotepadTabStat> for SummaryTab {
+    fn from(value: &NotepadTabStat) -> Self {
+        Self {
+            tab_guid: value.tab_guid().unwrap_or_default(),
+            tabstate_path: value.tabstate_path.clone(),
+            seq_number: value.seq_number,
+            is_saved_file: value.is_saved_file,
+            path: value.path.clone(),
+            last_write_time: value
+                .last_write_time
+                .as_ref()
+                .map(|t| FileTime::from(DateTime::<Utc>::from(t))),
+            contain_unsaved_data: value.contain_unsaved_data,
+            has_unsaved_chunks: value.unsaved_chunks.is_some(),
+            last_activity: value
+                .tabstate_times
+                .as_ref()
+                .and_then(|times| times.modified.as_ref())
+                .map(DateTime::<Utc>::from),
+        }
+    }
+}
+
+/// Summary of all the tabs found in one `TabState` directory, ordered by `seq_number`. The
+/// `TabState` files don't record the window their tab belongs to, so the tabs of every Notepad
+/// window of a profile end up in the same summary
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TabStateSummary {
+    pub schema_version: SchemaVersion,
+    pub tabstate_dir: String,
+    pub tabs_count: usize,
+    pub saved_tabs: usize,
+    pub unsaved_tabs: usize,
+    /// Latest `last_activity` of all the tabs
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::timestamps::serialize_datetime"
+    )]
+    pub last_activity: Option<DateTime<Utc>>,
+    pub tabs: Vec<SummaryTab>,
+    /// Files in the directory that failed to parse
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl TabStateSummary {
+    /// Build the summary from the already parsed `records` of the directory `tabstate_dir`
+    pub fn from_records(tabstate_dir: &str, records: &[NotepadTabStat]) -> Self {
+        let mut tabs = records.iter().map(SummaryTab::from).collect::<Vec<_>>();
+        tabs.sort_by(|a, b| {
+            a.seq_number
+                .cmp(&b.seq_number)
+                .then_with(|| a.tab_guid.cmp(&b.tab_guid))
+        });
+
+        let saved_tabs = tabs.iter().filter(|tab| tab.is_saved_file).count();
+        let last_activity = tabs.iter().filter_map(|tab| tab.last_activity).max();
+
+        Self {
+            schema_version: SchemaVersion,
+            tabstate_dir: tabstate_dir.to_string(),
+            tabs_count: tabs.len(),
+            saved_tabs,
+            unsaved_tabs: tabs.len() - saved_tabs,
+            last_activity,
+            tabs,
+            errors: vec![],
+        }
+    }
+
+    /// Parse all the `<GUID>.bin` files in the `TabState` directory `path`
+    pub fn from_dir(path: &str) -> std::result::Result<Self, NotepadErrors> {
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => return Err(NotepadErrors::FileOpen(e.to_string(), path.to_string())),
+        };
+
+        let mut records = vec![];
+        let mut errors = vec![];
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            let is_tab_file = entry_path
+                .file_name()
+                .map(|name| is_tab_file_name(&name.to_string_lossy()))
+                .unwrap_or(false);
+            if !is_tab_file {
+                continue;
+            }
+            match NotepadTabStat::from_path(&entry_path.to_string_lossy()) {
+                Ok(record) => records.push(record),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        let mut summary = Self::from_records(path, &records);
+        summary.errors = errors;
+        Ok(summary)
+    }
+}
@@ -1,11 +1,41 @@
-use crate::unsaved_chunks::UnsavedChunks;
+use crate::analysis::{
+    classify_input, edit_sessions, pastes, EditKind, InputEvent, DEFAULT_PASTE_MIN_LENGTH,
+};
+use crate::diff::{diff_hunks, word_diff, DiffTag};
+use crate::enums::{CRType, Encoding};
+use crate::errors::NotepadErrors;
+use crate::lint::{lint, LintCode, LintSeverity, LintWarning};
+use crate::markdown::{escape_markdown, fenced_block, MarkdownSink};
+use crate::output::{
+    safe_cell, ChunkCsvRecord, CsvRecord, OutputSink, SinkOptions, SinkRegistry, SummaryCsvRecord,
+    UTF8_BOM,
+};
+use crate::package::NotepadPackage;
+use crate::position::{map_position, PositionIndex};
+use crate::research::{ResearchRecord, UndecodedFieldsStats};
+use crate::schema::{record_schemas, SCHEMA_VERSION};
+use crate::settings::{AppSettingData, AppSettings};
+use crate::summary::TabStateSummary;
+use crate::timeline::{content_preview, BodyfileEntry, TimelineEvent, PREVIEW_CHARS};
+use crate::timestamps::{parse_filetime, system_time_to_filetime, FileSystemTimes};
+use crate::unsaved_chunks::{EditOperation, UnsavedChunks};
 use crate::NotepadTabStat;
 use glob::glob;
-use serde_json;
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+use winparsingtools::{date_time::FileTime, utils::bytes_to_hex};
 
 const SAMPLES_DIR_NAME: &str = "samples";
 
 //Start: Utils
+#[allow(clippy::useless_conversion, clippy::len_zero)]
 fn get_paths_from_glob(glob_path: &str) -> Vec<String> {
     let res = glob(glob_path)
         .unwrap()
@@ -28,6 +58,7 @@ fn check_word_wrap(data: &NotepadTabStat) -> bool {
     data.config_block.word_wrap
 }
 
+#[allow(clippy::redundant_pattern_matching)]
 fn check_unsaved_chunks(data: &NotepadTabStat) -> bool {
     match data.unsaved_chunks {
         Some(_) => true,
@@ -44,6 +75,34 @@ fn check_contain_unsaved_data(data: &NotepadTabStat) -> bool {
     data.contain_unsaved_data
 }
 
+/// FNV-1a hash of the JSON Schemas without their descriptions and the `schema_version` values,
+/// so only changes to the fields change it
+fn schema_fingerprint(schema: &serde_json::Value) -> u64 {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(object) => {
+                object.shift_remove("description");
+                for key in ["const", "default"] {
+                    if object.get(key).and_then(|value| value.as_str()) == Some(SCHEMA_VERSION) {
+                        object.shift_remove(key);
+                    }
+                }
+                object.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(array) => array.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut schema = schema.clone();
+    strip(&mut schema);
+    schema
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
 // End: Utils
 
 #[cfg(test)]
@@ -390,3 +449,1821 @@ fn tabstat_sample_saved_arabic_unsaved_mod() {
 }
 
 // End: Arabic language test
+
+// Start: Research mode tests
+
+#[cfg(test)]
+#[test]
+fn tabstat_undecoded_fields_offsets() {
+    let path = format!("./{}/*/*/*/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let raw = std::fs::read(&path).unwrap();
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        assert_eq!(
+            data.undecoded_fields.len(),
+            3,
+            "Unexpected number of undecoded fields for '{}'",
+            path
+        );
+        for field in &data.undecoded_fields {
+            let size = field.value.len() / 2;
+            let offset = field.offset as usize;
+            assert_eq!(
+                bytes_to_hex(&raw[offset..offset + size].to_vec()),
+                field.value,
+                "Value of '{}' doesn't match the bytes at offset {}",
+                field.field,
+                field.offset
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tabstat_undecoded_fields_unsaved() {
+    let path = format!(
+        "./{}/not_saved/english/rtl_unset/9f7c2ef9-5635-4629-a936-d388ca307aac.bin",
+        SAMPLES_DIR_NAME
+    );
+    let data = NotepadTabStat::from_path(&path).unwrap();
+    assert_eq!(data.path_size, 1);
+    let path_size = data
+        .undecoded_fields
+        .iter()
+        .find(|field| field.field == "path_size")
+        .unwrap();
+    assert_eq!(path_size.offset, 4);
+    assert_eq!(path_size.value, "01");
+}
+
+#[cfg(test)]
+#[test]
+fn tabstat_undecoded_fields_stats() {
+    let path = format!("./{}/*/*/*/*.bin", SAMPLES_DIR_NAME);
+    let paths = get_paths_from_glob(&path);
+    let mut stats = UndecodedFieldsStats::new();
+    for path in &paths {
+        stats.add(&NotepadTabStat::from_path(path).unwrap());
+    }
+    assert_eq!(stats.files, paths.len() as u64);
+    let config_unknown0_total: u64 = stats.fields["ConfigBlock::unknown0"].values().sum();
+    assert_eq!(config_unknown0_total, paths.len() as u64);
+
+    let data = NotepadTabStat::from_path(&paths[0]).unwrap();
+    let json = serde_json::to_value(ResearchRecord::from(&data)).unwrap();
+    assert!(json.get("undecoded_fields").is_some());
+    assert!(serde_json::to_value(&data)
+        .unwrap()
+        .get("undecoded_fields")
+        .is_none());
+}
+
+// End: Research mode tests
+
+// Start: Summary tests
+
+#[cfg(test)]
+#[test]
+fn summary_from_dir() {
+    let path = format!("./{}/saved/english/rtl_unset", SAMPLES_DIR_NAME);
+    let summary = TabStateSummary::from_dir(&path).unwrap();
+    assert_eq!(summary.tabs_count, 1);
+    assert_eq!(summary.saved_tabs, 1);
+    assert_eq!(summary.unsaved_tabs, 0);
+    assert!(summary.errors.is_empty());
+    assert_eq!(
+        summary.tabs[0].tab_guid,
+        "22bb38a5-0bf4-4ff5-8aec-b821a534b3e0"
+    );
+    assert!(summary.tabs[0].last_activity.is_some());
+    assert!(summary.last_activity.is_some());
+}
+
+#[cfg(test)]
+#[test]
+fn summary_from_records_ordered_by_seq_number() {
+    let path = format!("./{}/*/*/*/*.bin", SAMPLES_DIR_NAME);
+    let records = get_paths_from_glob(&path)
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let mut record = NotepadTabStat::from_path(path).unwrap();
+            record.seq_number = 100 - i as u64;
+            record
+        })
+        .collect::<Vec<NotepadTabStat>>();
+    let summary = TabStateSummary::from_records("TabState", &records);
+    assert_eq!(summary.tabs_count, records.len());
+    assert!(summary
+        .tabs
+        .windows(2)
+        .all(|tabs| tabs[0].seq_number <= tabs[1].seq_number));
+}
+
+// End: Summary tests
+
+// Start: Settings tests
+
+/// Build a minimal `settings.dat` hive with a `LocalState` key containing a few values
+fn build_settings_hive() -> Vec<u8> {
+    let mut bins: Vec<u8> = vec![];
+    bins.extend_from_slice(b"hbin");
+    bins.resize(0x20, 0);
+
+    let mut push_cell = |data: &[u8]| -> u32 {
+        let offset = bins.len() as u32;
+        let size = (data.len() + 4 + 7) & !7;
+        bins.extend_from_slice(&(-(size as i32)).to_le_bytes());
+        bins.extend_from_slice(data);
+        bins.resize(offset as usize + size, 0);
+        offset
+    };
+
+    let vk = |name: &str, data_size: u32, data_offset: u32, data_type: u32| -> Vec<u8> {
+        let mut vk = b"vk".to_vec();
+        vk.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        vk.extend_from_slice(&data_size.to_le_bytes());
+        vk.extend_from_slice(&data_offset.to_le_bytes());
+        vk.extend_from_slice(&data_type.to_le_bytes());
+        vk.extend_from_slice(&1u16.to_le_bytes());
+        vk.extend_from_slice(&[0, 0]);
+        vk.extend_from_slice(name.as_bytes());
+        vk
+    };
+
+    let nk = |name: &str, subkeys: (u32, u32), values: (u32, u32)| -> Vec<u8> {
+        let mut nk = vec![0u8; 0x4C];
+        nk[0..2].copy_from_slice(b"nk");
+        nk[0x02..0x04].copy_from_slice(&0x20u16.to_le_bytes());
+        nk[0x14..0x18].copy_from_slice(&subkeys.0.to_le_bytes());
+        nk[0x1C..0x20].copy_from_slice(&subkeys.1.to_le_bytes());
+        nk[0x24..0x28].copy_from_slice(&values.0.to_le_bytes());
+        nk[0x28..0x2C].copy_from_slice(&values.1.to_le_bytes());
+        nk[0x48..0x4A].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        nk.extend_from_slice(name.as_bytes());
+        nk
+    };
+
+    let timestamp = 0x01DA_EC3A_2B5C_8000_u64.to_le_bytes();
+
+    let mut ghost_file = vec![0x01];
+    ghost_file.extend_from_slice(&timestamp);
+    let ghost_file_data = push_cell(&ghost_file);
+    let ghost_file = push_cell(&vk("GhostFile", 9, ghost_file_data, 0x5F5E10B));
+
+    let mut font_family = "Consolas\0"
+        .encode_utf16()
+        .flat_map(|c| c.to_le_bytes())
+        .collect::<Vec<u8>>();
+    font_family.extend_from_slice(&timestamp);
+    let font_family_size = font_family.len() as u32;
+    let font_family_data = push_cell(&font_family);
+    let font_family = push_cell(&vk(
+        "FontFamily",
+        font_family_size,
+        font_family_data,
+        0x5F5E10C,
+    ));
+
+    // REG_DWORD stored inside the offset field
+    let theme = push_cell(&vk("Theme", 0x8000_0004, 2, 0x04));
+
+    let values = [ghost_file, font_family, theme]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<u8>>();
+    let values_list = push_cell(&values);
+    let local_state = push_cell(&nk("LocalState", (0, 0), (3, values_list)));
+
+    let mut lf = b"lf".to_vec();
+    lf.extend_from_slice(&1u16.to_le_bytes());
+    lf.extend_from_slice(&local_state.to_le_bytes());
+    lf.extend_from_slice(b"Loca");
+    let subkeys_list = push_cell(&lf);
+    let root = push_cell(&nk("{root}", (1, subkeys_list), (0, 0)));
+
+    let mut hive = vec![0u8; 0x1000];
+    hive[0..4].copy_from_slice(b"regf");
+    hive[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+    hive.extend_from_slice(&bins);
+    hive
+}
+
+#[cfg(test)]
+#[test]
+fn settings_parse_hive() {
+    let hive = build_settings_hive();
+    let settings = AppSettings::from_reader(&mut &hive[..]).unwrap();
+    assert_eq!(settings.values.len(), 3);
+
+    let ghost_file = settings.value("LocalState", "GhostFile").unwrap();
+    assert_eq!(ghost_file.data_type, "Boolean");
+    assert!(ghost_file.timestamp.is_some());
+
+    let notepad_settings = settings.notepad_settings();
+    assert_eq!(
+        notepad_settings.session_restore,
+        Some(AppSettingData::Bool(true))
+    );
+    assert_eq!(
+        notepad_settings.font_family,
+        Some(AppSettingData::String("Consolas".to_string()))
+    );
+    assert_eq!(notepad_settings.theme, Some(AppSettingData::UInt(2)));
+    assert_eq!(notepad_settings.font_size, None);
+}
+
+#[cfg(test)]
+#[test]
+fn settings_cell_loops() {
+    // Root key at 0x30 with its subkeys list at 0x20
+    let hive = |list: &[u8]| -> Vec<u8> {
+        let mut bins = b"hbin".to_vec();
+        bins.resize(0x20, 0);
+        bins.extend_from_slice(&(-0x10i32).to_le_bytes());
+        bins.extend_from_slice(list);
+        bins.resize(0x30, 0);
+        let mut nk = vec![0u8; 0x50];
+        nk[0..4].copy_from_slice(&(-0x50i32).to_le_bytes());
+        nk[4..6].copy_from_slice(b"nk");
+        nk[0x18..0x1C].copy_from_slice(&2u32.to_le_bytes());
+        nk[0x20..0x24].copy_from_slice(&0x20u32.to_le_bytes());
+        bins.extend_from_slice(&nk);
+
+        let mut hive = vec![0u8; 0x1000];
+        hive[0..4].copy_from_slice(b"regf");
+        hive[0x24..0x28].copy_from_slice(&0x30u32.to_le_bytes());
+        hive.extend_from_slice(&bins);
+        hive
+    };
+
+    // The root key is its own subkey, twice
+    let list = [b'l', b'i', 2, 0, 0x30, 0, 0, 0, 0x30, 0, 0, 0];
+    assert!(AppSettings::from_reader(&mut &hive(&list)[..]).is_err());
+    // The `ri` list contains itself
+    let list = [b'r', b'i', 2, 0, 0x20, 0, 0, 0, 0x20, 0, 0, 0];
+    assert!(AppSettings::from_reader(&mut &hive(&list)[..]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn settings_bad_signature() {
+    let data = [0u8; 0x1000];
+    assert!(AppSettings::from_reader(&mut &data[..]).is_err());
+}
+
+// End: Settings tests
+
+// Start: Package tests
+
+const NOTEPAD_MANIFEST: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Package xmlns="http://schemas.microsoft.com/appx/manifest/foundation/windows10">
+  <Identity Name="Microsoft.WindowsNotepad" Publisher="CN=Microsoft Corporation" Version="11.2407.9.0" ProcessorArchitecture="x64" />
+</Package>"#;
+
+#[cfg(test)]
+#[test]
+fn package_detect_from_evidence_root() {
+    // Glob characters in the root are matched literally
+    let root =
+        std::env::temp_dir().join(format!("notepad_parser_package_[{}]*?", std::process::id()));
+    let apps = root.join("Program Files").join("WindowsApps");
+    let latest = apps.join("Microsoft.WindowsNotepad_11.2407.9.0_x64__8wekyb3d8bbwe");
+    let older = apps.join("Microsoft.WindowsNotepad_11.2312.18.0_x64__8wekyb3d8bbwe");
+    std::fs::create_dir_all(&latest).unwrap();
+    std::fs::create_dir_all(&older).unwrap();
+    std::fs::write(latest.join("AppxManifest.xml"), NOTEPAD_MANIFEST).unwrap();
+
+    let from_root = NotepadPackage::detect(&root.to_string_lossy());
+    let from_folder = NotepadPackage::detect(&older.to_string_lossy());
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let from_root = from_root.unwrap();
+    assert_eq!(from_root.version, "11.2407.9.0");
+    assert_eq!(from_root.architecture.as_deref(), Some("x64"));
+    assert!(from_root.source.ends_with("AppxManifest.xml"));
+
+    // No manifest, the version is taken from the folder name
+    assert_eq!(from_folder.unwrap().version, "11.2312.18.0");
+}
+
+#[cfg(test)]
+#[test]
+fn package_from_full_name() {
+    assert!(NotepadPackage::from_full_name("Microsoft.WindowsNotepad_8wekyb3d8bbwe", "").is_none());
+    let package = NotepadPackage::from_full_name(
+        "Microsoft.WindowsNotepad_11.2407.9.0_arm64__8wekyb3d8bbwe",
+        "",
+    )
+    .unwrap();
+    assert_eq!(package.version, "11.2407.9.0");
+    assert_eq!(package.architecture.as_deref(), Some("arm64"));
+}
+
+// End: Package tests
+
+// Start: Edit operations tests
+
+#[cfg(test)]
+#[test]
+fn unsaved_chunks_operations() {
+    // Insert "ab" at 0, delete 1 char at 1, replace 1 char at 0 with "c"
+    let data: [u8; 0x1B] = [
+        0x00, 0x00, 0x02, 0x61, 0x00, 0x62, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x63, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let mut reader = &data[..];
+    let res = UnsavedChunks::from_reader(&mut reader).unwrap();
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].data(), Some("ab"));
+    assert_eq!(res.get(1).unwrap().num_of_deletion(), 1);
+    assert!(res.get(3).is_none());
+
+    let operations = res.operations().collect::<Vec<EditOperation>>();
+    assert_eq!(
+        operations,
+        vec![
+            EditOperation::Insert {
+                at: 0,
+                text: "ab".to_string()
+            },
+            EditOperation::Delete { at: 1, len: 1 },
+            EditOperation::Replace {
+                at: 0,
+                len: 1,
+                text: "c".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        (&res)
+            .into_iter()
+            .map(|chunk| chunk.position())
+            .sum::<u64>(),
+        1
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn unsaved_chunks_empty_operation() {
+    // A chunk at 2 that neither deletes nor adds characters
+    let data: [u8; 0x07] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    let mut reader = &data[..];
+    let res = UnsavedChunks::from_reader(&mut reader).unwrap();
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].operation(), EditOperation::NoOp { at: 2 });
+    assert_eq!(res[0].operation().at(), 2);
+}
+
+// End: Edit operations tests
+
+// Start: Position mapping tests
+
+#[cfg(test)]
+#[test]
+fn position_map_surrogates_and_line_breaks() {
+    // "a😀\rب" the emoji is a surrogate pair (2 UTF-16 units, 4 UTF-8 bytes)
+    let text = "a\u{1F600}\r\u{0628}c";
+    let position = map_position(text, 4, Some(&Encoding::UTF8), Some(&CRType::CRLF)).unwrap();
+    assert_eq!(position.char_index, 3);
+    assert_eq!(position.utf8_offset, 6);
+    assert_eq!(position.line, 2);
+    assert_eq!(position.column, 1);
+    // The `\r` is written as `\r\n` on disk
+    assert_eq!(position.file_offset, Some(7));
+
+    let position = map_position(text, 5, Some(&Encoding::UTF16LE), Some(&CRType::LF)).unwrap();
+    assert_eq!((position.line, position.column), (2, 2));
+    assert_eq!(position.file_offset, Some(12));
+    assert_eq!(position.to_string(), "line 2, column 2");
+
+    // Offset in the middle of the surrogate pair is mapped to the start of the character
+    let position = map_position(text, 2, None, None).unwrap();
+    assert_eq!(position.char_index, 1);
+    assert_eq!(position.file_offset, None);
+
+    assert!(map_position(text, 7, None, None).is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn position_index_matches_map_position() {
+    // Line breaks and surrogate pairs on both sides of the block boundaries
+    let text = "ab\r\n\u{1F600}\u{0628}\rc\n".repeat(400);
+    let inserts = ["\n", "\r", "\u{1F600}", "xyz\r\n", ""];
+    for (encoding, cr_type) in [
+        (Some(&Encoding::UTF8), Some(&CRType::CRLF)),
+        (Some(&Encoding::UTF16LE), None),
+        (None, None),
+    ] {
+        let mut buffer = text.encode_utf16().collect::<Vec<u16>>();
+        let mut index = PositionIndex::new(&text, encoding, cr_type);
+        let mut seed = 7u64;
+        for step in 0..300 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let position = (seed >> 33) as usize % (buffer.len() + 2);
+            let deletion = (seed >> 20) as usize % 4 * (step % 3);
+            let data = inserts[step % inserts.len()]
+                .encode_utf16()
+                .collect::<Vec<u16>>();
+
+            let current = String::from_utf16_lossy(&buffer);
+            for offset in [
+                position as u64,
+                0,
+                buffer.len() as u64,
+                buffer.len() as u64 + 1,
+            ] {
+                assert_eq!(
+                    index.position(offset),
+                    map_position(&current, offset, encoding, cr_type)
+                );
+            }
+
+            let start = position.min(buffer.len());
+            let end = (start + deletion).min(buffer.len());
+            assert_eq!(
+                index.units(start, end).as_deref(),
+                Some(&buffer[start..end])
+            );
+            buffer.splice(start..end, data.iter().copied());
+            index.splice(position, deletion, &data);
+        }
+        assert_eq!(index.units(0, buffer.len() + 1), None);
+        assert_eq!(index.into_units(), buffer);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn position_map_sample_cursor_and_chunks() {
+    let path = format!("./{}/saved/english/unsaved_mod/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        let cursor = data.cursor_start_location.as_ref().unwrap();
+        assert_eq!((cursor.line, cursor.column), (1, 40));
+
+        let chunks = data.unsaved_chunks.as_ref().unwrap();
+        assert!(chunks.iter().all(|chunk| chunk.location().is_some()));
+        // The first chunk adds a line break, the rest are typed on the second line
+        assert_eq!(chunks[1].location().unwrap().line, 2);
+        assert_eq!(
+            chunks.replay(&data.file_content),
+            "This is a test file, this file is saved\rThis line isn't saved"
+        );
+    }
+}
+
+// End: Position mapping tests
+
+// Start: Cursor text tests
+
+#[cfg(test)]
+#[test]
+fn cursor_selected_text_and_context() {
+    let mut data = NotepadTabStat {
+        file_content: "my password is hunter2 ok".to_string(),
+        cursor_start: Some(15),
+        cursor_end: Some(22),
+        ..Default::default()
+    };
+    data.update_cursor_text(3);
+    assert_eq!(
+        data.selected_text.as_ref().unwrap().saved.as_deref(),
+        Some("hunter2")
+    );
+    assert_eq!(
+        data.cursor_context.as_ref().unwrap().saved.as_deref(),
+        Some("is hunter2 ok")
+    );
+    assert!(data
+        .cursor_context
+        .as_ref()
+        .unwrap()
+        .reconstructed
+        .is_none());
+
+    // No selection
+    data.cursor_start = Some(22);
+    data.update_cursor_text(2);
+    assert!(data.selected_text.is_none());
+    assert_eq!(
+        data.cursor_context.as_ref().unwrap().saved.as_deref(),
+        Some("r2 o")
+    );
+
+    // A context bigger than the text doesn't overflow
+    data.update_cursor_text(usize::MAX);
+    assert_eq!(
+        data.cursor_context.unwrap().saved.as_deref(),
+        Some("my password is hunter2 ok")
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn cursor_context_reconstructed_buffer() {
+    let path = format!("./{}/not_saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let mut data = NotepadTabStat::from_path(&path).unwrap();
+        data.update_cursor_text(4);
+        let context = data.cursor_context.unwrap();
+        assert!(context.saved.is_none());
+        assert_eq!(context.reconstructed.as_deref(), Some("This"));
+    }
+}
+
+// End: Cursor text tests
+
+// Start: Input classification tests
+
+#[cfg(test)]
+#[test]
+fn classify_typing_and_pastes() {
+    // Type "ab" at 0, paste "xyz" at 2, delete at 4, type "c" at 4
+    let data: [u8; 0x2F] = [
+        0x00, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x62, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0x78, 0x00, 0x79, 0x00, 0x7A, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x01, 0x63, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+    let mut reader = &data[..];
+    let mut chunks = UnsavedChunks::from_reader(&mut reader).unwrap_or_else(|e| panic!("{}", e));
+    let replay = chunks.replay_mapped("", None, None);
+    chunks.set_locations(replay.locations);
+
+    let events = classify_input(&chunks, 3);
+    assert_eq!(events.len(), 3);
+    match &events[0] {
+        InputEvent::Typing(typing) => {
+            assert_eq!(typing.text, "ab");
+            assert_eq!(typing.keystrokes, 2);
+            assert_eq!((typing.first_chunk, typing.last_chunk), (0, 1));
+        }
+        e => panic!("Expected typing, found {:?}", e),
+    }
+    match &events[1] {
+        InputEvent::Paste(paste) => {
+            assert_eq!(paste.text, "xyz");
+            assert_eq!(paste.length, 3);
+            assert_eq!(paste.position, 2);
+            assert_eq!(paste.location.as_ref().unwrap().column, 3);
+        }
+        e => panic!("Expected paste, found {:?}", e),
+    }
+    assert!(matches!(&events[2], InputEvent::Typing(typing) if typing.text == "c"));
+    assert_eq!(pastes(&chunks, 3).len(), 1);
+    // Short additions like IME conversions and autocorrect are typing with the default length
+    assert!(pastes(&chunks, DEFAULT_PASTE_MIN_LENGTH).is_empty());
+    assert_eq!(classify_input(&chunks, DEFAULT_PASTE_MIN_LENGTH).len(), 2);
+
+    // Type "a" at 0, an emoji of two UTF-16 units at 1 and "b" at 3
+    let data: [u8; 0x1D] = [
+        0x00, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x3D, 0xD8, 0x00,
+        0xDE, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x62, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let mut reader = &data[..];
+    let chunks = UnsavedChunks::from_reader(&mut reader).unwrap_or_else(|e| panic!("{}", e));
+    let events = classify_input(&chunks, 3);
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        InputEvent::Typing(typing) => {
+            assert_eq!(typing.text, "a\u{1F600}b");
+            assert_eq!(typing.keystrokes, 3);
+        }
+        e => panic!("Expected typing, found {:?}", e),
+    }
+
+    // The emoji is a paste of one character with the length in characters
+    let pastes = pastes(&chunks, 1);
+    assert_eq!(pastes.len(), 3);
+    assert_eq!(pastes[1].text, "\u{1F600}");
+    assert_eq!(pastes[1].length, 1);
+}
+
+// End: Input classification tests
+
+// Start: Edit sessions tests
+
+#[cfg(test)]
+#[test]
+fn edit_sessions_merge_typing_and_backspaces() {
+    let data: [u8; 0x29] = [
+        0x1F, 0x00, 0x01, 0x0D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x01, 0x61, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x21, 0x00, 0x01, 0x62, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let mut reader = &data[..];
+    let chunks = UnsavedChunks::from_reader(&mut reader).unwrap();
+    let content = "File saved test\rFile saved test";
+
+    let sessions = edit_sessions(&chunks, Some(content));
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].kind, EditKind::Typed);
+    assert_eq!(sessions[0].text.as_deref(), Some("\rab"));
+    assert_eq!((sessions[0].first_chunk, sessions[0].last_chunk), (0, 2));
+    assert_eq!(sessions[1].kind, EditKind::Deleted);
+    assert_eq!(sessions[1].position, 32);
+    assert_eq!(sessions[1].deleted, 2);
+    assert_eq!(sessions[1].deleted_text.as_deref(), Some("ab"));
+    assert_eq!(
+        sessions[1].to_string(),
+        "deleted 2 chars (\"ab\") at offset 32"
+    );
+
+    // Without the content the deleted text can't be recovered
+    assert!(edit_sessions(&chunks, None)[1].deleted_text.is_none());
+    assert_eq!(
+        chunks.to_string(),
+        "typed \"\\rab\" at offset 31, then deleted 2 chars at offset 32"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn edit_sessions_overflowing_positions() {
+    // Type "a" at u64::MAX, type "b" at 0, delete 1 at u64::MAX, then delete 2 at u64::MAX
+    let max = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+    let mut data = vec![];
+    for (position, deletion, addition) in [
+        (&max[..], 0x00, &[0x61, 0x00][..]),
+        (&[0x00][..], 0x00, &[0x62, 0x00][..]),
+        (&max[..], 0x01, &[][..]),
+        (&max[..], 0x02, &[][..]),
+    ] {
+        data.extend_from_slice(position);
+        data.extend_from_slice(&[deletion, addition.len() as u8 / 2]);
+        data.extend_from_slice(addition);
+        data.extend_from_slice(&[0x00; 4]);
+    }
+    let mut reader = &data[..];
+    let chunks = UnsavedChunks::from_reader(&mut reader).unwrap();
+
+    let sessions = edit_sessions(&chunks, Some("abc"));
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions[2].deleted, 3);
+    assert!(sessions[2].deleted_text.is_none());
+    assert_eq!(classify_input(&chunks, DEFAULT_PASTE_MIN_LENGTH).len(), 2);
+
+    let data = NotepadTabStat {
+        unsaved_chunks: Some(chunks),
+        ..Default::default()
+    };
+    let out_of_range = lint(&data)
+        .into_iter()
+        .filter(|warning| warning.code == LintCode::ChunkOutOfRange)
+        .count();
+    assert_eq!(out_of_range, 3);
+}
+
+// End: Edit sessions tests
+
+// Start: Diff tests
+
+#[cfg(test)]
+#[test]
+fn diff_saved_content_and_buffer() {
+    let old = "line one\rline two\rline three";
+    let new = "line one\rline 2\rline three\rline four";
+
+    let hunks = diff_hunks(old, new);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!((hunks[0].old_start, hunks[0].old_lines), (1, 3));
+    assert_eq!((hunks[0].new_start, hunks[0].new_lines), (1, 4));
+    let changes = hunks[0]
+        .lines
+        .iter()
+        .filter(|line| line.tag != DiffTag::Equal)
+        .map(|line| (line.tag, line.text.as_str()))
+        .collect::<Vec<(DiffTag, &str)>>();
+    assert_eq!(
+        changes,
+        vec![
+            (DiffTag::Delete, "line two"),
+            (DiffTag::Delete, "line three"),
+            (DiffTag::Insert, "line 2"),
+            (DiffTag::Insert, "line three"),
+            (DiffTag::Insert, "line four"),
+        ]
+    );
+
+    assert_eq!(
+        word_diff("the secret is abc", "the secret is xyz now"),
+        "the secret is [-abc-]{+xyz now+}"
+    );
+    assert!(diff_hunks(old, old).is_empty());
+
+    // Only the final line break is missing
+    let hunks = diff_hunks("line one\r", "line one");
+    let lines = hunks[0]
+        .lines
+        .iter()
+        .map(|line| (line.tag, line.text.as_str(), line.no_newline_at_end))
+        .collect::<Vec<(DiffTag, &str, bool)>>();
+    assert_eq!(
+        lines,
+        vec![
+            (DiffTag::Delete, "line one", false),
+            (DiffTag::Insert, "line one", true),
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn diff_sample_saved_unsaved_mod() {
+    let path = format!("./{}/saved/english/unsaved_mod/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        assert_eq!(data.diff_hunks.as_ref().unwrap().len(), 1);
+        let unified = data.unified_diff().unwrap();
+        assert!(unified.contains("+This line isn't saved"));
+    }
+
+    let path = format!("./{}/not_saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        assert!(data.diff_hunks.is_none());
+        assert!(data.unified_diff().is_none());
+    }
+}
+
+// End: Diff tests
+
+// Start: Lint tests
+
+#[cfg(test)]
+#[test]
+fn lint_samples_have_no_warnings() {
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        let warnings = data
+            .warnings
+            .iter()
+            .flatten()
+            .filter(|warning| warning.code.severity() > LintSeverity::Info)
+            .collect::<Vec<&LintWarning>>();
+        assert!(warnings.is_empty(), "{}: {:?}", path, warnings);
+
+        // Notepad doesn't set the unsaved data flag of tabs with unsaved chunks
+        let unset = data
+            .warnings
+            .iter()
+            .flatten()
+            .any(|warning| warning.code == LintCode::UnsavedDataUnset);
+        assert_eq!(unset, check_unsaved_chunks(&data), "{}", path);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn lint_suspicious_values() {
+    let path = format!("./{}/saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    let path = &get_paths_from_glob(&path)[0];
+    let mut data = NotepadTabStat::from_path(path).unwrap();
+    data.tabstate_times = None;
+    data.encoding = Some(Encoding::UNKNOWN(0x09));
+    data.cr_type = Some(CRType::UNKNOWN(0x07));
+    data.cursor_end = Some(data.file_content_size + 1);
+    data.last_write_time = Some(FileTime::new(0x0300_0000_0000_1234));
+
+    let codes = lint(&data)
+        .iter()
+        .map(|warning| warning.code)
+        .collect::<Vec<LintCode>>();
+    assert_eq!(
+        codes,
+        vec![
+            LintCode::UnknownEncoding,
+            LintCode::UnknownCrType,
+            LintCode::CursorOutOfRange,
+            LintCode::FutureTimestamp
+        ]
+    );
+
+    let mut data = NotepadTabStat::from_path(path).unwrap();
+    data.tabstate_times = None;
+    data.file_size = data.file_size.map(|size| size + 1);
+    let warnings = lint(&data);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, LintCode::ContentSizeMismatch);
+    assert_eq!(
+        warnings[0].to_string(),
+        "content_size_mismatch: The file size is 40 bytes but the saved content is 39 bytes"
+    );
+
+    // Delete one character at position 5 of an empty buffer
+    let chunk = [0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00];
+    let data = NotepadTabStat {
+        file_content: String::new(),
+        unsaved_chunks: Some(UnsavedChunks::from_reader(&mut &chunk[..]).unwrap()),
+        ..Default::default()
+    };
+    let warnings = lint(&data);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].code, LintCode::ChunkOutOfRange);
+    assert_eq!(warnings[0].field, "unsaved_chunks");
+    assert_eq!(warnings[1].code, LintCode::UnsavedDataUnset);
+    assert_eq!(warnings[1].code.severity(), LintSeverity::Info);
+
+    let data = NotepadTabStat {
+        contain_unsaved_data: true,
+        ..data
+    };
+    assert_eq!(lint(&data).len(), 1);
+}
+
+#[cfg(test)]
+#[test]
+fn lint_timestamp_anomalies() {
+    let path = format!("./{}/saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    let path = &get_paths_from_glob(&path)[0];
+    let mut data = NotepadTabStat::from_path(path).unwrap();
+    assert!(data.tabstate_times.as_ref().unwrap().modified.is_some());
+
+    // 2024-08-11T16:55:41.1234567Z
+    let last_write_time = 0x01DA_EC0F_4C65_7B07_u64;
+    data.last_write_time = Some(FileTime::new(last_write_time));
+    data.tabstate_times = Some(FileSystemTimes {
+        modified: Some(FileTime::new(last_write_time - 10_000_000)),
+        ..Default::default()
+    });
+    let warnings = lint(&data);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, LintCode::WriteTimeAfterTabState);
+
+    data.tabstate_times = Some(FileSystemTimes {
+        modified: Some(FileTime::new(last_write_time + 10_000_000)),
+        created: Some(FileTime::new(
+            last_write_time - last_write_time % 10_000_000,
+        )),
+        ..Default::default()
+    });
+    // Zeroed filesystem times can come from copying the file
+    assert!(lint(&data).is_empty());
+
+    data.last_write_time = Some(FileTime::new(
+        last_write_time - last_write_time % 10_000_000,
+    ));
+    let warnings = lint(&data);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, LintCode::ZeroedSubseconds);
+    assert_eq!(warnings[0].field, "last_write_time");
+}
+
+#[cfg(test)]
+#[test]
+fn filesystem_time_overflow() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_723_395_341);
+    assert_eq!(system_time_to_filetime(time), Some(0x01DA_EC0F_4C52_A480));
+    // Far future times that don't fit in a FILETIME
+    let time = UNIX_EPOCH + Duration::from_secs(u64::MAX / 1_000);
+    assert_eq!(system_time_to_filetime(time), None);
+}
+
+// End: Lint tests
+
+// Start: Deserialize tests
+
+#[cfg(test)]
+#[test]
+fn deserialize_records_round_trip() {
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        let deserialized = serde_json::from_str::<NotepadTabStat>(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&deserialized).unwrap(),
+            json,
+            "{}",
+            path
+        );
+    }
+
+    let path = format!("./{}/saved/english/unsaved_mod/*.bin", SAMPLES_DIR_NAME);
+    let path = &get_paths_from_glob(&path)[0];
+    let data = NotepadTabStat::from_path(path).unwrap();
+    let json = serde_json::to_string(&ResearchRecord::from(&data)).unwrap();
+    let mut deserialized = serde_json::from_str::<NotepadTabStat>(&json).unwrap();
+    assert_eq!(deserialized.undecoded_fields, data.undecoded_fields);
+    // The reconstructed buffer isn't serialized, it is replayed from the chunks
+    assert!(deserialized.reconstructed_content().is_none());
+    deserialized.update_reconstructed_content();
+    assert!(deserialized.reconstructed_content().is_some());
+    assert_eq!(
+        deserialized.reconstructed_content(),
+        data.reconstructed_content()
+    );
+    assert!(matches!(deserialized.encoding, Some(Encoding::UTF8)));
+
+    // Summary records are not tab records
+    let summary = TabStateSummary::from_records("TabState", &[data]);
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(serde_json::from_str::<NotepadTabStat>(&json).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn deserialize_filetime() {
+    let time = parse_filetime("2024-08-11T16:55:41Z").unwrap();
+    assert_eq!(time.to_string(), "2024-08-11T16:55:41Z");
+    assert!(parse_filetime("11/08/2024").is_none());
+
+    let json = r#"{"modified":"2024-08-25T19:22:08Z","created":"2024-08-11T16:55:41.5Z"}"#;
+    let times = serde_json::from_str::<FileSystemTimes>(json).unwrap();
+    assert_eq!(times.modified.unwrap().to_string(), "2024-08-25T19:22:08Z");
+    assert!(times.accessed.is_none());
+    assert!(serde_json::from_str::<FileSystemTimes>(r#"{"modified":"now"}"#).is_err());
+
+    // The fraction of the second survives serializing and deserializing
+    let time = 0x01DA_EC0F_4C65_7B07_u64;
+    let data = NotepadTabStat {
+        last_write_time: Some(FileTime::new(time)),
+        tabstate_times: Some(FileSystemTimes {
+            modified: Some(FileTime::new(time + 10)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let json = serde_json::to_value(&data).unwrap();
+    assert_eq!(json["last_write_time"], "2024-08-11T16:55:41.123456Z");
+    assert_eq!(
+        json["tabstate_times"]["modified"],
+        "2024-08-11T16:55:41.123457Z"
+    );
+    let deserialized = serde_json::from_value::<NotepadTabStat>(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&deserialized).unwrap(), json);
+
+    // The CSV rows and the summaries keep it too
+    let csv = serde_json::to_value(CsvRecord::from(&data)).unwrap();
+    assert_eq!(csv["last_write_time"], "2024-08-11T16:55:41.123456Z");
+    assert_eq!(csv["tabstate_modified"], "2024-08-11T16:55:41.123457Z");
+
+    // The reports write the timestamps in the same format
+    for format in ["html", "markdown"] {
+        let output = SharedOutput::default();
+        let mut sink = SinkRegistry::default()
+            .create(format, Box::new(output.clone()), &SinkOptions::default())
+            .unwrap();
+        sink.record(&data).unwrap();
+        let report = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert!(report.contains("2024-08-11T16:55:41.123456Z"), "{}", format);
+        assert!(report.contains("2024-08-11T16:55:41.123457Z"), "{}", format);
+    }
+
+    let summary = TabStateSummary::from_records("TabState", &[data]);
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["last_activity"], "2024-08-11T16:55:41.123457Z");
+    assert_eq!(
+        json["tabs"][0]["last_write_time"],
+        "2024-08-11T16:55:41.123456Z"
+    );
+    assert_eq!(
+        SummaryCsvRecord::from(&summary).last_activity.unwrap(),
+        "2024-08-11T16:55:41.123457Z"
+    );
+}
+
+// End: Deserialize tests
+
+// Start: Schema tests
+
+#[cfg(test)]
+#[test]
+fn schema_covers_serialized_fields() {
+    let schemas = record_schemas();
+    // Validate against the schema, including its `required` list, and check that every
+    // serialized field is described
+    let validate = |record_type: &str, json: &serde_json::Value| {
+        let schema = serde_json::to_value(&schemas[record_type]).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors = validator
+            .iter_errors(json)
+            .map(|e| format!("{} at '{}'", e, e.instance_path))
+            .collect::<Vec<String>>();
+        assert!(errors.is_empty(), "{}: {:?}", record_type, errors);
+
+        let properties = &schemas[record_type]
+            .schema
+            .object
+            .as_ref()
+            .unwrap()
+            .properties;
+        for key in json.as_object().unwrap().keys() {
+            assert!(properties.contains_key(key), "{}", key);
+        }
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    };
+
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    let mut records = vec![];
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        validate("tab", &serde_json::to_value(&data).unwrap());
+        validate(
+            "research",
+            &serde_json::to_value(ResearchRecord::from(&data)).unwrap(),
+        );
+        validate(
+            "csv",
+            &serde_json::to_value(CsvRecord::from(&data)).unwrap(),
+        );
+        for chunk in ChunkCsvRecord::from_record(&data) {
+            validate("csv-chunks", &serde_json::to_value(chunk).unwrap());
+        }
+        records.push(data);
+    }
+
+    let summary = TabStateSummary::from_records("TabState", &records);
+    validate("summary", &serde_json::to_value(&summary).unwrap());
+    validate(
+        "summary-csv",
+        &serde_json::to_value(SummaryCsvRecord::from(&summary)).unwrap(),
+    );
+
+    // Any change to the schema fields, apart from their descriptions, needs a new schema version.
+    // Add the version with the printed fingerprint here after bumping `SCHEMA_VERSION`
+    let versions: [(&str, u64); 1] = [("1.2", 0xa859_8945_f81e_b8c4)];
+    let (version, fingerprint) = versions[versions.len() - 1];
+    let current = schema_fingerprint(&serde_json::to_value(&schemas).unwrap());
+    assert_eq!(
+        current, fingerprint,
+        "The schema fields changed since version {}, bump SCHEMA_VERSION and add {:#x}",
+        version, current
+    );
+    assert_eq!(version, SCHEMA_VERSION);
+
+    // The version of deserialized records is replaced with the current one
+    let mut json = serde_json::to_value(&records[0]).unwrap();
+    json["schema_version"] = "1.0".into();
+    let data = serde_json::from_value::<NotepadTabStat>(json.clone()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&data).unwrap()["schema_version"],
+        SCHEMA_VERSION
+    );
+    json.as_object_mut().unwrap().remove("schema_version");
+    assert!(serde_json::from_value::<NotepadTabStat>(json.clone()).is_ok());
+
+    // Records of another major version are rejected
+    for version in ["0.1", "2.0", "1", "latest"] {
+        json["schema_version"] = version.into();
+        let e = serde_json::from_value::<NotepadTabStat>(json.clone()).unwrap_err();
+        assert!(
+            e.to_string().starts_with(&format!(
+                "unsupported schema version '{}', expected a 1.x version",
+                version
+            )),
+            "{}",
+            e
+        );
+    }
+    json["schema_version"] = 1.into();
+    assert!(serde_json::from_value::<NotepadTabStat>(json).is_err());
+}
+
+// End: Schema tests
+
+// Start: Chunk checksum tests
+
+#[cfg(test)]
+#[test]
+fn unsaved_chunks_checksum() {
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    for path in get_paths_from_glob(&path) {
+        let data = NotepadTabStat::from_path(&path).unwrap();
+        for chunk in data.unsaved_chunks.iter().flatten() {
+            assert_eq!(chunk.checksum_valid(), Some(true), "{}", path);
+        }
+    }
+
+    #[rustfmt::skip]
+    let data = [
+        0x00, 0x00, 0x02, 0x61, 0x00, 0x62, 0x00, 0x33, 0xFD, 0x15, 0x5D,
+        0x00, 0x00, 0x02, 0x61, 0x00, 0x63, 0x00, 0x33, 0xFD, 0x15, 0x5D,
+    ];
+    let chunks = UnsavedChunks::from_reader(&mut &data[..]).unwrap();
+    assert_eq!(chunks[0].checksum(), "33FD155D");
+    assert_eq!(chunks[0].checksum_valid(), Some(true));
+    assert_eq!(chunks[1].checksum_valid(), Some(false));
+
+    let json = serde_json::to_string(&chunks).unwrap();
+    let json = json.replace(r#","checksum_valid":false"#, "");
+    let chunks = serde_json::from_str::<UnsavedChunks>(&json).unwrap();
+    assert_eq!(chunks[1].checksum_valid(), None);
+}
+
+// End: Chunk checksum tests
+
+// Start: Output sink tests
+
+/// Output shared with the test after the sink takes ownership of it
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn output_safe_cell() {
+    assert_eq!(safe_cell("=HYPERLINK(\"x\")"), "'=HYPERLINK(\"x\")");
+    assert_eq!(safe_cell("-1"), "'-1");
+    assert_eq!(safe_cell("\tcmd"), "'\tcmd");
+    assert_eq!(safe_cell("line 1\r\nline 2"), "line 1\\r\\nline 2");
+    assert_eq!(safe_cell("a=b"), "a=b");
+    assert_eq!(safe_cell("C:\\new\n"), "C:\\\\new\\n");
+    assert_eq!(safe_cell(""), "");
+}
+
+#[cfg(test)]
+#[test]
+fn output_csv_sink() {
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    let records = get_paths_from_glob(&path)
+        .iter()
+        .map(|path| NotepadTabStat::from_path(path).unwrap())
+        .collect::<Vec<NotepadTabStat>>();
+
+    for safe_csv in [false, true] {
+        let output = SharedOutput::default();
+        let options = SinkOptions {
+            csv_bom: true,
+            safe_csv,
+            ..Default::default()
+        };
+        let mut sink = SinkRegistry::default()
+            .create("csv", Box::new(output.clone()), &options)
+            .unwrap();
+        sink.begin().unwrap();
+        for record in &records {
+            sink.record(record).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let bytes = output.0.borrow();
+        assert!(bytes.starts_with(UTF8_BOM));
+        let mut reader = csv::Reader::from_reader(&bytes[UTF8_BOM.len()..]);
+        assert_eq!(reader.headers().unwrap().get(0), Some("schema_version"));
+        assert_eq!(reader.records().count(), records.len());
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn output_sink_registry() {
+    struct CountSink(Arc<AtomicUsize>);
+    impl OutputSink for CountSink {
+        fn record(&mut self, _: &NotepadTabStat) -> Result<(), NotepadErrors> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+        fn summary(&mut self, _: &TabStateSummary) -> Result<(), NotepadErrors> {
+            Ok(())
+        }
+        fn finish(&mut self) -> Result<(), NotepadErrors> {
+            Ok(())
+        }
+    }
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut registry = SinkRegistry::default();
+    assert_eq!(registry.names()[..3], ["jsonl", "csv", "csv-chunks"]);
+    #[cfg(feature = "sqlite")]
+    assert!(registry.names().contains(&"sqlite"));
+    assert!(registry
+        .create("xml", Box::new(std::io::sink()), &SinkOptions::default())
+        .is_err());
+
+    let counter = count.clone();
+    registry.register("count", move |_, _| {
+        Ok(Box::new(CountSink(counter.clone())))
+    });
+    let mut sink = registry
+        .create("count", Box::new(std::io::sink()), &SinkOptions::default())
+        .unwrap();
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    let paths = get_paths_from_glob(&path);
+    for path in &paths {
+        sink.record(&NotepadTabStat::from_path(path).unwrap())
+            .unwrap();
+    }
+    assert_eq!(count.load(Ordering::SeqCst), paths.len());
+}
+
+// End: Output sink tests
+
+// Start: SQLite tests
+
+#[cfg(all(test, feature = "sqlite"))]
+#[test]
+fn sqlite_sink() {
+    let path = format!("./{}/**/*.bin", SAMPLES_DIR_NAME);
+    let paths = get_paths_from_glob(&path);
+    let output = SharedOutput::default();
+    let mut sink = SinkRegistry::default()
+        .create("sqlite", Box::new(output.clone()), &SinkOptions::default())
+        .unwrap();
+    sink.begin().unwrap();
+    let mut chunks = 0;
+    for path in &paths {
+        let data = NotepadTabStat::from_path(path).unwrap();
+        chunks += data
+            .unsaved_chunks
+            .as_ref()
+            .map_or(0, |chunks| chunks.len());
+        sink.record(&data).unwrap();
+    }
+    sink.error("missing.bin", &NotepadErrors::NA).unwrap();
+    sink.finish().unwrap();
+
+    let db_path = std::env::temp_dir().join(format!("sqlite_sink_{}.sqlite", std::process::id()));
+    std::fs::write(&db_path, output.0.borrow().as_slice()).unwrap();
+    let connection = rusqlite::Connection::open(&db_path).unwrap();
+    let count = |sql: &str| -> usize { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM files"), paths.len());
+    assert_eq!(
+        count("SELECT COUNT(*) FROM tabs JOIN config ON config.tab_id = tabs.id"),
+        paths.len()
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM chunks"), chunks);
+    assert_eq!(count("SELECT COUNT(*) FROM errors"), 1);
+    assert!(count("SELECT COUNT(*) FROM search WHERE search MATCH 'saved'") > 0);
+    // The typed characters are searchable as words, not one chunk at a time
+    assert!(
+        count("SELECT COUNT(*) FROM search WHERE search MATCH 'test' AND source = 'edit_text'") > 0
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM search WHERE source = 'chunk'"),
+        0
+    );
+    assert_eq!(count("SELECT COUNT(raw) FROM tabs"), 0);
+    assert_eq!(count("SELECT COUNT(tabstate_size) FROM files"), paths.len());
+
+    // The fields of the records are typed columns
+    let path = format!("./{}/saved/english/unsaved_mod/*.bin", SAMPLES_DIR_NAME);
+    let data = NotepadTabStat::from_path(&get_paths_from_glob(&path)[0]).unwrap();
+    let location = data.cursor_start_location.as_ref().unwrap();
+    let row = connection
+        .query_row(
+            "SELECT tabs.seq_number, tabs.path_size, tabs.file_content_size,
+                tabs.cursor_start_line, tabs.cursor_start_column, tabs.cursor_start_file_offset
+            FROM tabs JOIN files ON files.id = tabs.file_id WHERE files.tabstate_path = ?1",
+            [data.tabstate_path.as_ref().unwrap()],
+            |row| {
+                Ok([
+                    row.get::<_, u64>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ])
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        row,
+        [
+            data.seq_number,
+            data.path_size,
+            data.file_content_size,
+            location.line,
+            location.column,
+            location.file_offset.unwrap()
+        ]
+    );
+    // The times keep the fraction of the second
+    let last_write_time: String = connection
+        .query_row(
+            "SELECT tabs.last_write_time FROM tabs JOIN files ON files.id = tabs.file_id
+            WHERE files.tabstate_path = ?1",
+            [data.tabstate_path.as_ref().unwrap()],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(last_write_time, "2024-08-11T17:00:32.978184Z");
+    drop(connection);
+    std::fs::remove_file(&db_path).unwrap();
+
+    // The database is written to the output path directly, the output stream isn't used
+    let db_path =
+        std::env::temp_dir().join(format!("sqlite_sink_path_{}.sqlite", std::process::id()));
+    std::fs::write(&db_path, b"not a database").unwrap();
+    let options = SinkOptions {
+        research: true,
+        output_path: Some(db_path.clone()),
+        ..Default::default()
+    };
+    let output = SharedOutput::default();
+    let mut sink = SinkRegistry::default()
+        .create("sqlite", Box::new(output.clone()), &options)
+        .unwrap();
+    sink.begin().unwrap();
+    for path in &paths {
+        sink.record(&NotepadTabStat::from_path(path).unwrap())
+            .unwrap();
+    }
+    sink.finish().unwrap();
+    drop(sink);
+    assert!(output.0.borrow().is_empty());
+    let connection = rusqlite::Connection::open(&db_path).unwrap();
+    let count = |sql: &str| -> usize { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(raw) FROM tabs"), paths.len());
+    drop(connection);
+    std::fs::remove_file(&db_path).unwrap();
+}
+
+// End: SQLite tests
+
+// Start: Columnar tests
+
+#[cfg(all(test, feature = "parquet"))]
+#[test]
+fn columnar_sinks() {
+    use crate::{
+        columnar::{tab_schema, BATCH_SIZE},
+        settings::NotepadSettings,
+    };
+    use arrow_array::{
+        cast::AsArray, types::UInt64Type, Array, ListArray, StructArray, TimestampMicrosecondArray,
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let path = format!("./{}/saved/english/unsaved_mod/*.bin", SAMPLES_DIR_NAME);
+    let mut data = NotepadTabStat::from_path(&get_paths_from_glob(&path)[0]).unwrap();
+    // Values the sample doesn't have
+    data.app_settings = Some(NotepadSettings {
+        theme: Some(AppSettingData::UInt(2)),
+        ..Default::default()
+    });
+    data.warnings = Some(vec![LintWarning::new(
+        LintCode::ZeroedSubseconds,
+        "last_write_time",
+        "message".to_string(),
+    )]);
+
+    // Every field of the records has a column, the config block and the times are flattened
+    let schema = tab_schema(false);
+    for key in serde_json::to_value(&data)
+        .unwrap()
+        .as_object()
+        .unwrap()
+        .keys()
+    {
+        assert!(
+            ["config_block", "tabstate_times"].contains(&key.as_str())
+                || schema.field_with_name(key).is_ok(),
+            "{}",
+            key
+        );
+    }
+    let records = BATCH_SIZE * 2 + 1;
+    let write = |format: &str| {
+        let output = SharedOutput::default();
+        let mut sink = SinkRegistry::default()
+            .create(format, Box::new(output.clone()), &SinkOptions::default())
+            .unwrap();
+        sink.begin().unwrap();
+        for _ in 0..records {
+            sink.record(&data).unwrap();
+        }
+        sink.finish().unwrap();
+        let bytes = output.0.borrow().clone();
+        bytes
+    };
+
+    let parquet_path =
+        std::env::temp_dir().join(format!("columnar_sinks_{}.parquet", std::process::id()));
+    std::fs::write(&parquet_path, write("parquet")).unwrap();
+    let parquet =
+        ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&parquet_path).unwrap())
+            .unwrap();
+    assert_eq!(parquet.metadata().num_row_groups(), 3);
+    let batches = parquet
+        .build()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    std::fs::remove_file(&parquet_path).unwrap();
+    let arrow =
+        arrow_ipc::reader::StreamReader::try_new(std::io::Cursor::new(write("arrow")), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+    for batches in [batches, arrow] {
+        assert_eq!(
+            batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+            records
+        );
+        let batch = &batches[0];
+        let last_write_time = batch
+            .column_by_name("last_write_time")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(
+            last_write_time.value(0),
+            chrono::DateTime::<chrono::Utc>::from(data.last_write_time.as_ref().unwrap())
+                .timestamp_micros()
+        );
+        let chunks = batch
+            .column_by_name("unsaved_chunks")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(
+            chunks.value(0).len(),
+            data.unsaved_chunks.as_ref().unwrap().len()
+        );
+
+        let column = |array: &StructArray, name: &str| array.column_by_name(name).unwrap().clone();
+        let chunk = data.unsaved_chunks.as_ref().unwrap().iter().next().unwrap();
+        let location = column(chunks.value(0).as_struct(), "location");
+        assert_eq!(
+            column(location.as_struct(), "line")
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            chunk.location().unwrap().line
+        );
+        let cursor = batch.column_by_name("cursor_start_location").unwrap();
+        assert_eq!(
+            column(cursor.as_struct(), "column")
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            data.cursor_start_location.as_ref().unwrap().column
+        );
+        let context = batch.column_by_name("cursor_context").unwrap();
+        assert_eq!(
+            column(context.as_struct(), "reconstructed")
+                .as_string::<i32>()
+                .value(0),
+            data.cursor_context
+                .as_ref()
+                .unwrap()
+                .reconstructed
+                .as_deref()
+                .unwrap()
+        );
+        assert!(batch.column_by_name("selected_text").unwrap().is_null(0));
+
+        let sessions = batch
+            .column_by_name("edit_sessions")
+            .unwrap()
+            .as_list::<i32>();
+        assert_eq!(
+            sessions.value(0).len(),
+            data.edit_sessions.as_ref().unwrap().len()
+        );
+        let hunks = batch.column_by_name("diff_hunks").unwrap().as_list::<i32>();
+        let lines = column(hunks.value(0).as_struct(), "lines");
+        assert_eq!(
+            lines.as_list::<i32>().value(0).len(),
+            data.diff_hunks.as_ref().unwrap()[0].lines.len()
+        );
+        assert!(batch.column_by_name("pastes").unwrap().is_null(0));
+
+        let app_settings = batch.column_by_name("app_settings").unwrap();
+        let theme = column(app_settings.as_struct(), "theme");
+        assert_eq!(
+            column(theme.as_struct(), "uint")
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            2
+        );
+        assert!(column(theme.as_struct(), "bool").is_null(0));
+        assert!(column(app_settings.as_struct(), "font_size").is_null(0));
+        let warnings = batch.column_by_name("warnings").unwrap().as_list::<i32>();
+        assert_eq!(
+            column(warnings.value(0).as_struct(), "code")
+                .as_string::<i32>()
+                .value(0),
+            "zeroed_subseconds"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+#[test]
+fn columnar_batches_bounded_by_bytes() {
+    use crate::columnar::BATCH_BYTES;
+
+    // Two of these records fill a batch
+    let data = NotepadTabStat {
+        file_content: "a".repeat(BATCH_BYTES / 2),
+        ..Default::default()
+    };
+    let output = SharedOutput::default();
+    let mut sink = SinkRegistry::default()
+        .create("arrow", Box::new(output.clone()), &SinkOptions::default())
+        .unwrap();
+    sink.begin().unwrap();
+    for _ in 0..3 {
+        sink.record(&data).unwrap();
+    }
+    sink.finish().unwrap();
+
+    let bytes = output.0.borrow().clone();
+    let rows = arrow_ipc::reader::StreamReader::try_new(std::io::Cursor::new(bytes), None)
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .collect::<Vec<usize>>();
+    assert_eq!(rows, vec![2, 1]);
+}
+
+// End: Columnar tests
+
+// Start: XLSX tests
+#[cfg(all(test, feature = "xlsx"))]
+#[test]
+fn xlsx_sink() {
+    let output = SharedOutput::default();
+    let mut sink = SinkRegistry::default()
+        .create("xlsx", Box::new(output.clone()), &SinkOptions::default())
+        .unwrap();
+    sink.begin().unwrap();
+    for dir in ["saved/english/unsaved_mod", "saved/arabic/rtl_set"] {
+        let path = format!("./{}/{}/*.bin", SAMPLES_DIR_NAME, dir);
+        for path in get_paths_from_glob(&path) {
+            sink.record(&NotepadTabStat::from_path(&path).unwrap())
+                .unwrap();
+        }
+    }
+    // Excel rejects cells longer than 32,767 characters, the content is truncated
+    let path = format!("./{}/saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    let mut data = NotepadTabStat::from_path(&get_paths_from_glob(&path)[0]).unwrap();
+    data.file_content = "A".repeat(40_000);
+    sink.record(&data).unwrap();
+    sink.error(
+        "missing.bin",
+        &NotepadErrors::Output("test".to_string(), "test".to_string()),
+    )
+    .unwrap();
+    sink.finish().unwrap();
+
+    let bytes = output.0.borrow();
+    assert!(bytes.starts_with(b"PK\x03\x04"));
+}
+
+#[cfg(all(test, feature = "xlsx"))]
+#[test]
+fn xlsx_sink_rows_limit() {
+    use crate::xlsx::XlsxSink;
+
+    let path = format!("./{}/not_saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    let data = NotepadTabStat::from_path(&get_paths_from_glob(&path)[0]).unwrap();
+    let chunks = data.unsaved_chunks.as_ref().unwrap().len();
+    // The headers and the chunks of one record fit in a worksheet
+    let mut sink =
+        XlsxSink::with_max_rows(Box::new(SharedOutput::default()), chunks as u32 + 1).unwrap();
+    for _ in 0..2 {
+        sink.record(&data).unwrap();
+    }
+    // The chunks of the second record don't fit after the first ones and start a new worksheet
+    let names = sink.worksheet_names();
+    assert_eq!(
+        names
+            .iter()
+            .filter(|name| name.starts_with("Chunks"))
+            .collect::<Vec<&String>>(),
+        ["Chunks", "Chunks (2)"]
+    );
+    sink.finish().unwrap();
+
+    // A record with more chunks than a worksheet holds continues on the next worksheets
+    let mut sink = XlsxSink::with_max_rows(Box::new(SharedOutput::default()), 11).unwrap();
+    sink.record(&data).unwrap();
+    let worksheets = sink
+        .worksheet_names()
+        .iter()
+        .filter(|name| name.starts_with("Chunks"))
+        .count();
+    assert_eq!(worksheets, chunks.div_ceil(10));
+    sink.finish().unwrap();
+}
+// End: XLSX tests
+
+// Start: HTML tests
+#[cfg(test)]
+#[test]
+fn html_sink() {
+    let output = SharedOutput::default();
+    let mut sink = SinkRegistry::default()
+        .create("html", Box::new(output.clone()), &SinkOptions::default())
+        .unwrap();
+    sink.begin().unwrap();
+    for dir in ["not_saved/english/rtl_unset", "saved/arabic/unsaved_mod"] {
+        let path = format!("./{}/{}/*.bin", SAMPLES_DIR_NAME, dir);
+        for path in get_paths_from_glob(&path) {
+            sink.record(&NotepadTabStat::from_path(&path).unwrap())
+                .unwrap();
+        }
+    }
+    sink.error(
+        "<script>.bin",
+        &NotepadErrors::Output("test".to_string(), "test".to_string()),
+    )
+    .unwrap();
+    sink.finish().unwrap();
+
+    let html = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.ends_with("</html>\n"));
+    assert_eq!(html.matches("<section").count(), 3);
+    // The deleted space is taken from the edit sessions
+    assert!(html.contains("<td>deleted</td><td class=\"change\" dir=\"ltr\"><del> </del>"));
+    assert!(html.contains("<td>0-42</td><td>0</td><td>typed</td>"));
+    assert!(html.contains("<pre dir=\"rtl\">"));
+    assert!(html.contains("&lt;script&gt;.bin"));
+    assert!(!html.contains("<script"));
+}
+// End: HTML tests
+
+// Start: Markdown tests
+#[cfg(test)]
+#[test]
+fn markdown_escaping() {
+    assert_eq!(fenced_block("a\rb"), "```text\na\nb\n```\n");
+    // The fence is longer than the backticks in the content
+    assert_eq!(fenced_block("``` `"), "````text\n``` `\n````\n");
+    assert_eq!(
+        escape_markdown("C:\\Temp\\*new*.txt"),
+        "C:\\\\Temp\\\\\\*new\\*.txt"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn markdown_sink() {
+    let write = || {
+        let output = SharedOutput::default();
+        let mut sink = SinkRegistry::default()
+            .create(
+                "markdown",
+                Box::new(output.clone()),
+                &SinkOptions::default(),
+            )
+            .unwrap();
+        sink.begin().unwrap();
+        let path = format!("./{}/not_saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+        for path in get_paths_from_glob(&path) {
+            sink.record(&NotepadTabStat::from_path(&path).unwrap())
+                .unwrap();
+        }
+        sink.finish().unwrap();
+        let markdown = String::from_utf8(output.0.borrow().clone()).unwrap();
+        markdown
+    };
+
+    let markdown = write();
+    assert_eq!(markdown, write());
+    assert!(markdown.contains("## Unsaved tab 9f7c2ef9-5635-4629-a936-d388ca307aac\n"));
+    assert!(markdown.contains(
+        "### Reconstructed buffer\n\n```text\nThis is a test file, this file isn't saved\n```\n"
+    ));
+    assert!(markdown.contains("44 unsaved chunks in 2 edits"));
+    assert!(markdown.contains("| 1 | deleted | 43-43 | 42 |  | ` ` |\n"));
+
+    // Two deletions of almost u64::MAX characters
+    let count = [0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+    let mut data = vec![];
+    for _ in 0..2 {
+        data.push(0x00);
+        data.extend_from_slice(&count);
+        data.extend_from_slice(&[0x00; 5]);
+    }
+    let mut reader = &data[..];
+    let chunks = UnsavedChunks::from_reader(&mut reader).unwrap();
+    assert_eq!(
+        chunks.iter().next().unwrap().num_of_deletion(),
+        u64::MAX - 1
+    );
+    let output = SharedOutput::default();
+    let mut sink = MarkdownSink::new(Box::new(output.clone()));
+    sink.record(&NotepadTabStat {
+        unsaved_chunks: Some(chunks),
+        ..Default::default()
+    })
+    .unwrap();
+    let markdown = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert!(markdown.contains(&format!(
+        "0 characters added and {} characters deleted",
+        u64::MAX
+    )));
+
+    // The warnings checked against the time of the run are left out
+    let future = 0x0300_0000_0000_0000_u64;
+    let mut data = NotepadTabStat {
+        last_write_time: Some(FileTime::new(future - future % 10_000_000)),
+        ..Default::default()
+    };
+    data.warnings = Some(lint(&data));
+    assert_eq!(
+        data.warnings.as_ref().unwrap()[0].code,
+        LintCode::FutureTimestamp
+    );
+    let output = SharedOutput::default();
+    let mut sink = MarkdownSink::new(Box::new(output.clone()));
+    sink.record(&data).unwrap();
+    let markdown = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert!(!markdown.contains("future"));
+    assert!(markdown.contains("### Warnings\n\n- zeroed\\_subseconds: "));
+}
+// End: Markdown tests
+
+// Start: Timeline tests
+#[cfg(test)]
+#[test]
+fn timeline_bodyfile() {
+    let data = NotepadTabStat {
+        tabstate_path: Some("TabState\\22bb38a5-0bf4-4ff5-8aec-b821a534b3e0.bin".to_string()),
+        tabstate_times: Some(FileSystemTimes {
+            modified: parse_filetime("2024-08-11T16:56:00Z"),
+            created: parse_filetime("2024-08-11T16:50:00Z"),
+            ..Default::default()
+        }),
+        tabstate_size: Some(226),
+        path: Some("C:\\Windows\\Temp\\test.txt".to_string()),
+        file_size: Some(39),
+        last_write_time: parse_filetime("2024-08-11T16:55:41Z"),
+        ..Default::default()
+    };
+    let lines = BodyfileEntry::from_record(&data)
+        .iter()
+        .map(|entry| entry.to_line())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        lines,
+        [
+            "0|TabState\\22bb38a5-0bf4-4ff5-8aec-b821a534b3e0.bin|0|r/rrwxrwxrwx|0|0|226|0|1723395360|0|1723395000",
+            "0|C:\\Windows\\Temp\\test.txt|0|r/rrwxrwxrwx|0|0|39|0|1723395341|0|0",
+        ]
+    );
+
+    // Unsaved tabs have no referenced document
+    let data = NotepadTabStat::default();
+    assert!(BodyfileEntry::from_record(&data).is_empty());
+
+    // The size of the TabState file is read with its times
+    let path = format!("./{}/saved/english/rtl_unset/*.bin", SAMPLES_DIR_NAME);
+    let path = &get_paths_from_glob(&path)[0];
+    let data = NotepadTabStat::from_path(path).unwrap();
+    let size = std::fs::metadata(path).unwrap().len();
+    assert_eq!(data.tabstate_size, Some(size));
+    assert_eq!(BodyfileEntry::from_record(&data)[0].size, size);
+}
+
+#[cfg(test)]
+#[test]
+fn timeline_events() {
+    let data = NotepadTabStat {
+        tabstate_path: Some("TabState\\22bb38a5-0bf4-4ff5-8aec-b821a534b3e0.bin".to_string()),
+        path: Some("C:\\Windows\\Temp\\test.txt".to_string()),
+        is_saved_file: true,
+        last_write_time: parse_filetime("2024-08-11T16:55:41Z"),
+        file_content: "first|line\rsecond line".to_string(),
+        ..Default::default()
+    };
+    let events = TimelineEvent::from_record(&data);
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].to_tln(),
+        "1723395341|NOTEPAD|||[File Last Written] Notepad tab referencing C:\\Windows\\Temp\\test.txt, file last written; unsaved data: no; content: \"first_line second line\""
+    );
+    let l2t = events[0].to_l2t(&data);
+    assert_eq!(l2t[..4], ["08/11/2024", "16:55:41", "UTC", "M..."]);
+    assert_eq!(
+        l2t[9],
+        "Notepad tab referencing C:\\Windows\\Temp\\test.txt, file last written"
+    );
+    assert_eq!(
+        l2t[12],
+        "TabState\\22bb38a5-0bf4-4ff5-8aec-b821a534b3e0.bin"
+    );
+
+    // The preview is cut to a single line of `PREVIEW_CHARS` characters
+    let data = NotepadTabStat {
+        file_content: "A\r".repeat(PREVIEW_CHARS),
+        ..Default::default()
+    };
+    let preview = content_preview(&data);
+    assert_eq!(preview.chars().count(), PREVIEW_CHARS + 3);
+    assert!(preview.starts_with("A A ") && preview.ends_with("..."));
+}
+// End: Timeline tests
@@ -0,0 +1,329 @@
+use crate::{errors::NotepadErrors, output::OutputSink, summary::TabStateSummary, NotepadTabStat};
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use winparsingtools::date_time::FileTime;
+
+/// Mode written for the entries, the same as `fls` uses for regular files on NTFS
+const BODYFILE_MODE: &str = "r/rrwxrwxrwx";
+/// Number of characters of the content in the event descriptions
+pub const PREVIEW_CHARS: usize = 64;
+/// Source of the L2T CSV and TLN events
+const EVENT_SOURCE: &str = "NOTEPAD";
+const L2T_SOURCE_TYPE: &str = "Notepad TabState";
+const L2T_HEADERS: [&str; 17] = [
+    "date",
+    "time",
+    "timezone",
+    "MACB",
+    "source",
+    "sourcetype",
+    "type",
+    "user",
+    "host",
+    "short",
+    "desc",
+    "version",
+    "filename",
+    "inode",
+    "notes",
+    "format",
+    "extra",
+];
+
+fn timeline_error(e: impl ToString, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+/// Seconds since the Unix epoch, `0` for a missing time which `mactime` skips
+fn unix_time(time: Option<&FileTime>) -> i64 {
+    time.map(|time| DateTime::<Utc>::from(time).timestamp())
+        .unwrap_or_default()
+}
+
+/// A line in the bodyfile format of The Sleuth Kit 3.x:
+/// `MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime`
+pub struct BodyfileEntry<'a> {
+    pub name: &'a str,
+    pub size: u64,
+    pub atime: Option<&'a FileTime>,
+    pub mtime: Option<&'a FileTime>,
+    pub ctime: Option<&'a FileTime>,
+    pub crtime: Option<&'a FileTime>,
+}
+
+impl BodyfileEntry<'_> {
+    /// Entries for the filesystem times of the `TabState` file and the `last_write_time` of the
+    /// document it references, with the document path as the name
+    pub fn from_record(record: &NotepadTabStat) -> Vec<BodyfileEntry<'_>> {
+        let mut entries = vec![];
+        if let (Some(path), Some(times)) = (&record.tabstate_path, &record.tabstate_times) {
+            entries.push(BodyfileEntry {
+                name: path,
+                size: record.tabstate_size.unwrap_or_default(),
+                atime: times.accessed.as_ref(),
+                mtime: times.modified.as_ref(),
+                ctime: times.changed.as_ref(),
+                crtime: times.created.as_ref(),
+            });
+        }
+        if let (Some(path), Some(time)) = (&record.path, &record.last_write_time) {
+            entries.push(BodyfileEntry {
+                name: path,
+                size: record.file_size.unwrap_or_default(),
+                atime: None,
+                mtime: Some(time),
+                ctime: None,
+                crtime: None,
+            });
+        }
+        entries
+    }
+
+    pub fn to_line(&self) -> String {
+        format!(
+            "0|{}|0|{}|0|0|{}|{}|{}|{}|{}",
+            self.name.replace(['|', '\n', '\r'], "_"),
+            BODYFILE_MODE,
+            self.size,
+            unix_time(self.atime),
+            unix_time(self.mtime),
+            unix_time(self.ctime),
+            unix_time(self.crtime)
+        )
+    }
+}
+
+/// Bodyfile entries to merge into a `mactime` timeline. Summaries aren't written
+pub struct BodyfileSink {
+    output: Box<dyn Write>,
+}
+
+impl BodyfileSink {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self { output }
+    }
+}
+
+impl OutputSink for BodyfileSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        for entry in BodyfileEntry::from_record(record) {
+            writeln!(self.output, "{}", entry.to_line())
+                .map_err(|e| timeline_error(e, "Unable to write the bodyfile entry"))?;
+        }
+        Ok(())
+    }
+
+    fn summary(&mut self, _summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .flush()
+            .map_err(|e| timeline_error(e, "Unable to flush the output"))
+    }
+}
+
+/// The first `PREVIEW_CHARS` characters of the buffer on a single line. The reconstructed buffer
+/// is used if the tab has unsaved chunks
+pub fn content_preview(record: &NotepadTabStat) -> String {
+    let content = record
+        .reconstructed_content()
+        .unwrap_or(&record.file_content);
+    let content = content.split_whitespace().collect::<Vec<&str>>().join(" ");
+    match content.char_indices().nth(PREVIEW_CHARS) {
+        Some((index, _)) => format!("{}...", &content[..index]),
+        None => content,
+    }
+}
+
+/// A single timestamp of a tab for the L2T CSV and TLN timelines
+pub struct TimelineEvent<'a> {
+    pub time: &'a FileTime,
+    /// Which of the MACB times the event is, `M...` for a modification
+    pub macb: &'static str,
+    /// Description of the timestamp
+    pub event_type: &'static str,
+    /// The tab and what happened, without the content preview
+    pub short: String,
+    pub description: String,
+    pub tabstate_path: Option<&'a str>,
+}
+
+impl TimelineEvent<'_> {
+    /// Events for the `last_write_time` of the referenced document and the filesystem times of
+    /// the `TabState` file
+    pub fn from_record(record: &NotepadTabStat) -> Vec<TimelineEvent<'_>> {
+        let tab = match (&record.path, record.tab_guid()) {
+            (Some(path), _) => format!("Notepad tab referencing {}", path),
+            (None, Some(guid)) => format!("Notepad unsaved tab {}", guid),
+            (None, None) => "Notepad unsaved tab".to_string(),
+        };
+        let chunks = record.unsaved_chunks.as_ref().map_or(0, |c| c.len());
+        let unsaved = match (record.contain_unsaved_data || chunks > 0, chunks) {
+            (false, _) => "unsaved data: no".to_string(),
+            (true, 0) => "unsaved data: yes".to_string(),
+            (true, chunks) => format!("unsaved data: yes ({} unsaved chunks)", chunks),
+        };
+        let details = format!("{}; content: \"{}\"", unsaved, content_preview(record));
+
+        let times = record.tabstate_times.as_ref();
+        let candidates = [
+            (
+                record.last_write_time.as_ref(),
+                "M...",
+                "File Last Written",
+                "file last written",
+            ),
+            (
+                times.and_then(|t| t.modified.as_ref()),
+                "M...",
+                "TabState Modified",
+                "TabState file modified",
+            ),
+            (
+                times.and_then(|t| t.accessed.as_ref()),
+                ".A..",
+                "TabState Accessed",
+                "TabState file accessed",
+            ),
+            (
+                times.and_then(|t| t.changed.as_ref()),
+                "..C.",
+                "TabState Changed",
+                "TabState file changed",
+            ),
+            (
+                times.and_then(|t| t.created.as_ref()),
+                "...B",
+                "TabState Created",
+                "TabState file created",
+            ),
+        ];
+        candidates
+            .into_iter()
+            .filter_map(|(time, macb, event_type, action)| {
+                let short = format!("{}, {}", tab, action);
+                time.map(|time| TimelineEvent {
+                    time,
+                    macb,
+                    event_type,
+                    description: format!("{}; {}", short, details),
+                    short,
+                    tabstate_path: record.tabstate_path.as_deref(),
+                })
+            })
+            .collect()
+    }
+
+    /// A line in the TLN format: `Time|Source|System|User|Description`
+    pub fn to_tln(&self) -> String {
+        format!(
+            "{}|{}|||[{}] {}",
+            unix_time(Some(self.time)),
+            EVENT_SOURCE,
+            self.event_type,
+            self.description.replace(['|', '\n', '\r'], "_")
+        )
+    }
+
+    /// The columns of the L2T CSV format, see `L2T_HEADERS`
+    pub fn to_l2t(&self, record: &NotepadTabStat) -> [String; 17] {
+        let time = DateTime::<Utc>::from(self.time);
+        [
+            time.format("%m/%d/%Y").to_string(),
+            time.format("%H:%M:%S").to_string(),
+            "UTC".to_string(),
+            self.macb.to_string(),
+            EVENT_SOURCE.to_string(),
+            L2T_SOURCE_TYPE.to_string(),
+            self.event_type.to_string(),
+            "-".to_string(),
+            "-".to_string(),
+            self.short.clone(),
+            self.description.clone(),
+            "2".to_string(),
+            self.tabstate_path.unwrap_or("-").to_string(),
+            "-".to_string(),
+            "-".to_string(),
+            "notepad_parser".to_string(),
+            format!(
+                "tab_guid: {}; is_saved_file: {}; seq_number: {}",
+                record.tab_guid().unwrap_or_default(),
+                record.is_saved_file,
+                record.seq_number
+            ),
+        ]
+    }
+}
+
+/// Events in the log2timeline CSV format. Summaries aren't written
+pub struct L2tCsvSink {
+    writer: csv::Writer<Box<dyn Write>>,
+}
+
+impl L2tCsvSink {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(output),
+        }
+    }
+}
+
+impl OutputSink for L2tCsvSink {
+    fn begin(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.writer
+            .write_record(L2T_HEADERS)
+            .map_err(|e| timeline_error(e, "Unable to write the CSV headers"))
+    }
+
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        for event in TimelineEvent::from_record(record) {
+            self.writer
+                .write_record(event.to_l2t(record))
+                .map_err(|e| timeline_error(e, "Unable to write the timeline event"))?;
+        }
+        Ok(())
+    }
+
+    fn summary(&mut self, _summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.writer
+            .flush()
+            .map_err(|e| timeline_error(e, "Unable to flush the output"))
+    }
+}
+
+/// Events in the TLN format of Harlan Carvey's timeline tools. Summaries aren't written
+pub struct TlnSink {
+    output: Box<dyn Write>,
+}
+
+impl TlnSink {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self { output }
+    }
+}
+
+impl OutputSink for TlnSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        for event in TimelineEvent::from_record(record) {
+            writeln!(self.output, "{}", event.to_tln())
+                .map_err(|e| timeline_error(e, "Unable to write the timeline event"))?;
+        }
+        Ok(())
+    }
+
+    fn summary(&mut self, _summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        self.output
+            .flush()
+            .map_err(|e| timeline_error(e, "Unable to flush the output"))
+    }
+}
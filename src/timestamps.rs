@@ -0,0 +1,180 @@
+use crate::errors::NotepadErrors;
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fs::{self, Metadata},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use winparsingtools::date_time::FileTime;
+
+/// Number of 100-nanosecond intervals between 1601-01-01 and 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// Convert a `SystemTime` to a FILETIME timestamp (100-nanosecond intervals since 1601-01-01)
+pub(crate) fn system_time_to_filetime(time: SystemTime) -> Option<u64> {
+    let duration = time.duration_since(UNIX_EPOCH).ok()?;
+    unix_parts_to_filetime(duration.as_secs(), duration.subsec_nanos() as u64)
+}
+
+/// FILETIME of `secs` seconds and `nsecs` nanoseconds after the unix epoch. Returns `None` if
+/// the time doesn't fit in a FILETIME
+fn unix_parts_to_filetime(secs: u64, nsecs: u64) -> Option<u64> {
+    secs.checked_mul(10_000_000)?
+        .checked_add(nsecs / 100)?
+        .checked_add(FILETIME_UNIX_EPOCH)
+}
+
+/// Copy a `FileTime`, it doesn't implement `Clone`
+pub(crate) fn copy_filetime(time: &FileTime) -> FileTime {
+    FileTime::from(DateTime::<Utc>::from(time))
+}
+
+/// Parse a `FileTime` serialized as an RFC 3339 timestamp, e.g. `2024-08-11T16:55:41Z`
+pub fn parse_filetime(value: &str) -> Option<FileTime> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| FileTime::from(time.with_timezone(&Utc)))
+}
+
+/// `time` as an RFC 3339 timestamp keeping the fraction of the second, e.g.
+/// `2024-08-11T16:55:41.123456Z`. Whole seconds are written without a fraction. All the sinks
+/// write text timestamps in this format
+pub(crate) fn datetime_text(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// `datetime_text` of a `FileTime`
+pub(crate) fn filetime_text(time: &FileTime) -> String {
+    datetime_text(&DateTime::<Utc>::from(time))
+}
+
+/// Serialize an optional `FileTime` with `filetime_text`
+pub(crate) fn serialize_filetime<S>(
+    time: &Option<FileTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match time {
+        Some(time) => serializer.serialize_some(&filetime_text(time)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Serialize an optional `DateTime` with `datetime_text`
+pub(crate) fn serialize_datetime<S>(
+    time: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match time {
+        Some(time) => serializer.serialize_some(&datetime_text(time)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserialize an optional `FileTime` written by `serialize_filetime` or its `Serialize`
+/// implementation
+pub(crate) fn deserialize_filetime<'de, D>(deserializer: D) -> Result<Option<FileTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) => match parse_filetime(&value) {
+            Some(time) => Ok(Some(time)),
+            None => Err(serde::de::Error::custom(format!(
+                "invalid timestamp '{}'",
+                value
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Convert a unix timestamp with nanoseconds to a FILETIME timestamp
+#[cfg(unix)]
+fn unix_time_to_filetime(secs: i64, nsecs: i64) -> Option<u64> {
+    let secs = u64::try_from(secs).ok()?;
+    let nsecs = u64::try_from(nsecs).ok()?;
+    unix_parts_to_filetime(secs, nsecs)
+}
+
+/// Filesystem timestamps of a file. Timestamps not supported by the platform or the filesystem are `None`
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
+pub struct FileSystemTimes {
+    /// Last modification time (mtime)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_filetime",
+        deserialize_with = "deserialize_filetime"
+    )]
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    pub modified: Option<FileTime>,
+    /// Last access time (atime)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_filetime",
+        deserialize_with = "deserialize_filetime"
+    )]
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    pub accessed: Option<FileTime>,
+    /// Last metadata change time (ctime), only available on unix
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_filetime",
+        deserialize_with = "deserialize_filetime"
+    )]
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    pub changed: Option<FileTime>,
+    /// Creation time (birth time)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_filetime",
+        deserialize_with = "deserialize_filetime"
+    )]
+    #[schemars(schema_with = "crate::schema::filetime_schema")]
+    pub created: Option<FileTime>,
+}
+
+impl FileSystemTimes {
+    /// Read the timestamps of the file at `path`
+    pub fn from_path(path: &str) -> std::result::Result<Self, NotepadErrors> {
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(Self::from(&metadata)),
+            Err(e) => Err(NotepadErrors::FileOpen(e.to_string(), path.to_string())),
+        }
+    }
+}
+
+impl From<&Metadata> for FileSystemTimes {
+    fn from(value: &Metadata) -> Self {
+        let filetime = |time: std::io::Result<SystemTime>| {
+            time.ok()
+                .and_then(system_time_to_filetime)
+                .map(FileTime::new)
+        };
+
+        #[cfg(unix)]
+        let changed = {
+            use std::os::unix::fs::MetadataExt;
+            unix_time_to_filetime(value.ctime(), value.ctime_nsec()).map(FileTime::new)
+        };
+        #[cfg(not(unix))]
+        let changed = Option::None;
+
+        Self {
+            modified: filetime(value.modified()),
+            accessed: filetime(value.accessed()),
+            changed,
+            created: filetime(value.created()),
+        }
+    }
+}
@@ -1,12 +1,45 @@
-use crate::NotepadErrors;
-use serde::Serialize;
+use crate::{
+    analysis::edit_sessions,
+    enums::{CRType, Encoding},
+    position::{PositionIndex, TextPosition},
+    NotepadErrors,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
     io::{self, Read},
+    ops::Index,
+    slice::Iter,
 };
 use winparsingtools::utils::{bytes_to_hex, read_uleb128, read_utf16_string};
 
-#[derive(Debug, Serialize)]
+/// The edit operation an `UnsavedChunk` applies to the buffer
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub enum EditOperation {
+    /// Insert `text` at position `at`
+    Insert { at: u64, text: String },
+    /// Delete `len` characters starting at position `at`
+    Delete { at: u64, len: u64 },
+    /// Delete `len` characters starting at position `at` and insert `text` in their place
+    Replace { at: u64, len: u64, text: String },
+    /// Neither delete nor insert anything at position `at`
+    NoOp { at: u64 },
+}
+
+impl EditOperation {
+    /// Position in the buffer where the operation is applied
+    pub fn at(&self) -> u64 {
+        match self {
+            EditOperation::Insert { at, .. }
+            | EditOperation::Delete { at, .. }
+            | EditOperation::Replace { at, .. }
+            | EditOperation::NoOp { at } => *at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UnsavedChunk {
     position: u64,
     num_of_deletion: u64,
@@ -14,10 +47,20 @@ pub struct UnsavedChunk {
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<String>,
     checksum: String,
+    /// `true` if `checksum` matches the CRC32 of the chunk data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum_valid: Option<bool>,
+    /// `position` mapped against the buffer before applying this chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<TextPosition>,
 }
 
 impl UnsavedChunk {
     pub fn from_reader<R: Read>(reader: &mut R) -> std::result::Result<Self, NotepadErrors> {
+        // Keep the bytes of the chunk to verify the checksum
+        let mut recorder = RecordingReader::new(reader);
+        let reader = &mut recorder;
+
         // Read `position`. This is the cursor position where the data will be deleted from or added to
         let position = match read_uleb128(reader) {
             Ok(pos) => pos,
@@ -70,8 +113,10 @@ impl UnsavedChunk {
             },
         };
 
+        // Read `checksum`. CRC32 checksum of the chunk bytes stored as big endian
+        let crc32 = crc32fast::hash(&recorder.data);
         let mut checksum = [0u8; 4];
-        if let Err(e) = reader.read_exact(&mut checksum) {
+        if let Err(e) = recorder.inner.read_exact(&mut checksum) {
             return Err(NotepadErrors::ReadError(
                 e.to_string(),
                 "checksum".to_string(),
@@ -84,11 +129,102 @@ impl UnsavedChunk {
             num_of_addition,
             data,
             checksum: bytes_to_hex(&checksum.to_vec()),
+            checksum_valid: Some(u32::from_be_bytes(checksum) == crc32),
+            location: Option::None,
         })
     }
+
+    /// Apply the chunk to `buffer` of UTF-16 code units. Positions outside the buffer are clamped
+    pub fn apply(&self, buffer: &mut Vec<u16>) {
+        let start = (self.position as usize).min(buffer.len());
+        let end = start
+            .saturating_add(self.num_of_deletion as usize)
+            .min(buffer.len());
+        let data = self.data.as_deref().unwrap_or_default().encode_utf16();
+        buffer.splice(start..end, data);
+    }
+
+    /// `position` mapped to characters and line/column, available after parsing through `NotepadTabStat`
+    pub fn location(&self) -> Option<&TextPosition> {
+        self.location.as_ref()
+    }
+
+    /// The cursor position where the data is deleted from or added to
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Number of characters deleted
+    pub fn num_of_deletion(&self) -> u64 {
+        self.num_of_deletion
+    }
+
+    /// Number of characters added
+    pub fn num_of_addition(&self) -> u64 {
+        self.num_of_addition
+    }
+
+    /// The added text, `None` if nothing is added
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    /// CRC32 checksum of the chunk as hex
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+
+    /// `true` if `checksum` matches the chunk bytes, `None` if it isn't verified
+    pub fn checksum_valid(&self) -> Option<bool> {
+        self.checksum_valid
+    }
+
+    /// Get the typed edit operation of this chunk
+    pub fn operation(&self) -> EditOperation {
+        let text = self.data.clone().unwrap_or_default();
+        match (self.num_of_deletion, self.num_of_addition) {
+            (0, 0) => EditOperation::NoOp { at: self.position },
+            (0, _) => EditOperation::Insert {
+                at: self.position,
+                text,
+            },
+            (len, 0) => EditOperation::Delete {
+                at: self.position,
+                len,
+            },
+            (len, _) => EditOperation::Replace {
+                at: self.position,
+                len,
+                text,
+            },
+        }
+    }
+}
+
+/// Wraps a reader and keeps a copy of the bytes read
+struct RecordingReader<'a, R> {
+    inner: &'a mut R,
+    data: Vec<u8>,
+}
+
+impl<'a, R: Read> RecordingReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        Self {
+            inner,
+            data: vec![],
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+impl<R: Read> Read for RecordingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.data.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UnsavedChunks(Vec<UnsavedChunk>);
 
 impl UnsavedChunks {
@@ -111,44 +247,123 @@ impl UnsavedChunks {
             }
         }
 
-        if unsaved_chunks.len() > 0 {
+        if !unsaved_chunks.is_empty() {
             Ok(Self(unsaved_chunks))
         } else {
-            return Err(NotepadErrors::NA);
+            Err(NotepadErrors::NA)
+        }
+    }
+
+    /// Number of chunks
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&UnsavedChunk> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> Iter<'_, UnsavedChunk> {
+        self.0.iter()
+    }
+
+    /// Apply all the chunks in order on top of `content` and return the resulting buffer
+    pub fn replay(&self, content: &str) -> String {
+        let mut buffer = content.encode_utf16().collect::<Vec<u16>>();
+        for chunk in &self.0 {
+            chunk.apply(&mut buffer);
+        }
+        String::from_utf16_lossy(&buffer)
+    }
+
+    /// Apply all the chunks in order on top of `content` in a single pass, keeping the location
+    /// and the deleted text of each chunk in the buffer it is applied to
+    pub(crate) fn replay_mapped(
+        &self,
+        content: &str,
+        encoding: Option<&Encoding>,
+        cr_type: Option<&CRType>,
+    ) -> Replay {
+        let mut index = PositionIndex::new(content, encoding, cr_type);
+        let mut locations = Vec::with_capacity(self.0.len());
+        let mut deleted_texts = Vec::with_capacity(self.0.len());
+        for chunk in &self.0 {
+            locations.push(index.position(chunk.position));
+            let deleted_text = match chunk.num_of_deletion {
+                0 => None,
+                len => chunk
+                    .position
+                    .checked_add(len)
+                    .and_then(|end| index.units(chunk.position as usize, end as usize))
+                    .map(|units| String::from_utf16_lossy(&units)),
+            };
+            deleted_texts.push(deleted_text);
+            let data = chunk.data.as_deref().unwrap_or_default();
+            index.splice(
+                chunk.position as usize,
+                chunk.num_of_deletion as usize,
+                &data.encode_utf16().collect::<Vec<u16>>(),
+            );
+        }
+        Replay {
+            content: String::from_utf16_lossy(&index.into_units()),
+            locations,
+            deleted_texts,
         }
     }
+
+    /// Set the `location` of each chunk, see `replay_mapped`
+    pub(crate) fn set_locations(&mut self, locations: Vec<Option<TextPosition>>) {
+        for (chunk, location) in self.0.iter_mut().zip(locations) {
+            chunk.location = location;
+        }
+    }
+
+    /// Iterate over the typed edit operations in the order they were applied
+    pub fn operations(&self) -> impl Iterator<Item = EditOperation> + '_ {
+        self.0.iter().map(UnsavedChunk::operation)
+    }
+}
+
+/// The result of `UnsavedChunks::replay_mapped`
+pub(crate) struct Replay {
+    /// The buffer after applying all the chunks
+    pub(crate) content: String,
+    /// Location of each chunk in the buffer it is applied to
+    pub(crate) locations: Vec<Option<TextPosition>>,
+    /// Text deleted by each chunk, `None` if it deletes nothing or outside the buffer
+    pub(crate) deleted_texts: Vec<Option<String>>,
+}
+
+impl Index<usize> for UnsavedChunks {
+    type Output = UnsavedChunk;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a UnsavedChunks {
+    type Item = &'a UnsavedChunk;
+    type IntoIter = Iter<'a, UnsavedChunk>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
 }
 
 impl Display for UnsavedChunks {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut previous_addition = 0;
-        let data = self
-            .0
+        let sessions = edit_sessions(self, None)
             .iter()
-            .map(|x| {
-                let mut chunk = String::from("");
-                if x.num_of_addition > 0 {
-                    if previous_addition == 0 {
-                        previous_addition = x.position;
-                        chunk.push_str(&format!("[{}]:{}", x.position, &x.data.clone().unwrap()));
-                    } else if x.position == (previous_addition + 1) {
-                        chunk.push_str(&x.data.clone().unwrap());
-                        previous_addition = x.position;
-                    } else {
-                        chunk.push_str(&format!(",[{}]:{}", x.position, &x.data.clone().unwrap()));
-                        previous_addition = x.position;
-                    }
-                } else {
-                    if previous_addition > 0 {
-                        previous_addition = previous_addition - 1;
-                    }
-                    chunk.push_str(&format!("<DEL:{}>", x.position));
-                }
-                format!("{}", chunk)
-            })
+            .map(|session| session.to_string())
             .collect::<Vec<String>>()
-            .join("");
+            .join(", then ");
 
-        write!(f, "{}", data)
+        write!(f, "{}", sessions)
     }
 }
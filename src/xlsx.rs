@@ -0,0 +1,512 @@
+use crate::{
+    errors::NotepadErrors,
+    output::{ChunkCsvRecord, CsvRecord, OutputSink, SummaryCsvRecord},
+    schema::{SchemaVersion, SCHEMA_VERSION},
+    summary::TabStateSummary,
+    NotepadTabStat,
+};
+use chrono::{DateTime, Utc};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use winparsingtools::date_time::FileTime;
+
+/// Excel limits a cell to this number of characters, longer text is truncated
+const MAX_CELL_CHARS: usize = 32_767;
+const TRUNCATED_SUFFIX: &str = " [truncated]";
+/// Widest column width in characters, wider text is cut in the view but kept in the cell
+const MAX_COLUMN_WIDTH: usize = 60;
+const DATETIME_FORMAT: &str = "yyyy-mm-dd hh:mm:ss";
+/// Columns of the CSV records that aren't useful in a report
+const SKIPPED_COLUMNS: [&str; 1] = ["raw"];
+
+fn xlsx_error(e: XlsxError, msg: &str) -> NotepadErrors {
+    NotepadErrors::Output(e.to_string(), msg.to_string())
+}
+
+fn truncate_cell(text: &str) -> String {
+    match text
+        .char_indices()
+        .nth(MAX_CELL_CHARS - TRUNCATED_SUFFIX.len())
+    {
+        Some((index, _)) => format!("{}{}", &text[..index], TRUNCATED_SUFFIX),
+        None => text.to_string(),
+    }
+}
+
+/// A problem found in a tab or a file that failed to parse
+#[derive(Serialize)]
+struct LintRow<'a> {
+    tabstate_path: &'a str,
+    tab_guid: Option<String>,
+    code: String,
+    field: &'a str,
+    message: String,
+}
+
+/// Excel limits a worksheet to this number of rows, including the headers
+const MAX_ROWS: u32 = 1_048_576;
+
+/// Names of the serialized fields of `row`, used as the headers of its sheet
+fn columns<T: Serialize>(row: &T) -> Vec<String> {
+    match serde_json::to_value(row) {
+        Ok(Value::Object(fields)) => fields
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| !SKIPPED_COLUMNS.contains(&name.as_str()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// A worksheet with its headers, its next row and the widths of its columns. When the
+/// worksheet is full the rows continue in a new worksheet named `<name> (2)`, `<name> (3)`...
+struct Sheet {
+    name: String,
+    headers: Vec<String>,
+    max_rows: u32,
+    /// Worksheets that reached `max_rows`
+    full: Vec<Worksheet>,
+    worksheet: Worksheet,
+    row: u32,
+    widths: Vec<usize>,
+}
+
+impl Sheet {
+    /// Create the sheet and write `headers` to its first row
+    fn new(
+        name: &str,
+        headers: Vec<String>,
+        max_rows: u32,
+        formats: &Formats,
+    ) -> std::result::Result<Self, NotepadErrors> {
+        let mut sheet = Self {
+            name: name.to_string(),
+            headers,
+            max_rows,
+            full: vec![],
+            worksheet: Worksheet::new(),
+            row: 0,
+            widths: vec![],
+        };
+        sheet
+            .start_worksheet(name, formats)
+            .map_err(|e| xlsx_error(e, "Unable to create the worksheet"))?;
+        Ok(sheet)
+    }
+
+    /// Name the current worksheet `name` and write the headers
+    fn start_worksheet(
+        &mut self,
+        name: &str,
+        formats: &Formats,
+    ) -> std::result::Result<(), XlsxError> {
+        self.worksheet.set_name(name)?.set_freeze_panes(1, 0)?;
+        for (col, header) in self.headers.iter().enumerate() {
+            self.worksheet
+                .write_string_with_format(0, col as u16, header, &formats.header)?;
+        }
+        self.widths = self
+            .headers
+            .iter()
+            .map(|header| header.chars().count().min(MAX_COLUMN_WIDTH))
+            .collect();
+        self.row = 1;
+        Ok(())
+    }
+
+    /// Continue the rows in a new worksheet
+    fn roll_over(&mut self, formats: &Formats) -> std::result::Result<(), XlsxError> {
+        let full = std::mem::replace(&mut self.worksheet, Worksheet::new());
+        self.full.push(set_widths(full, &self.widths)?);
+        let name = format!("{} ({})", self.name, self.full.len() + 1);
+        self.start_worksheet(&name, formats)
+    }
+
+    /// Roll over to a new worksheet if the current one can't hold `rows` more rows, so the rows
+    /// of a record are kept together
+    fn reserve(&mut self, rows: usize, formats: &Formats) -> std::result::Result<(), XlsxError> {
+        let available = self.max_rows.saturating_sub(self.row) as usize;
+        match self.row > 1 && rows > available {
+            true => self.roll_over(formats),
+            false => Ok(()),
+        }
+    }
+
+    /// `true` if no rows were written to the sheet
+    fn is_empty(&self) -> bool {
+        self.full.is_empty() && self.row <= 1
+    }
+
+    fn fit(&mut self, col: u16, chars: usize) {
+        let col = col as usize;
+        if self.widths.len() <= col {
+            self.widths.resize(col + 1, 0);
+        }
+        self.widths[col] = self.widths[col].max(chars.min(MAX_COLUMN_WIDTH));
+    }
+
+    /// Write the fields of `row` to the next row, in the order of the headers. Fields named in
+    /// `times` are written as datetime cells and text is right-to-left with `rtl`
+    fn write<T: Serialize>(
+        &mut self,
+        row: &T,
+        times: &[(&str, Option<&FileTime>)],
+        rtl: bool,
+        formats: &Formats,
+    ) -> std::result::Result<(), XlsxError> {
+        let mut fields = match serde_json::to_value(row) {
+            Ok(Value::Object(fields)) => fields,
+            _ => return Ok(()),
+        };
+        let fields = self
+            .headers
+            .iter()
+            .map(|name| (name.clone(), fields.remove(name).unwrap_or(Value::Null)))
+            .collect::<Vec<(String, Value)>>();
+
+        if self.row >= self.max_rows {
+            self.roll_over(formats)?;
+        }
+
+        let text_format = match rtl {
+            true => &formats.rtl,
+            false => &formats.text,
+        };
+        for (col, (name, value)) in fields.iter().enumerate() {
+            let col = col as u16;
+            let time = times
+                .iter()
+                .find(|(time_name, _)| time_name == name)
+                .map(|(_, time)| time);
+            match (time, value) {
+                (Some(Some(time)), _) => {
+                    let time = DateTime::<Utc>::from(*time).naive_utc();
+                    self.worksheet.write_datetime_with_format(
+                        self.row,
+                        col,
+                        time,
+                        &formats.datetime,
+                    )?;
+                    self.fit(col, DATETIME_FORMAT.len());
+                }
+                (_, Value::Null) | (Some(None), _) => {}
+                (_, Value::Bool(value)) => {
+                    self.worksheet.write_boolean(self.row, col, *value)?;
+                    self.fit(col, 5);
+                }
+                (_, Value::Number(value)) => {
+                    self.worksheet.write_number(
+                        self.row,
+                        col,
+                        value.as_f64().unwrap_or_default(),
+                    )?;
+                    self.fit(col, value.to_string().len());
+                }
+                (_, Value::String(value)) => {
+                    let value = truncate_cell(value);
+                    self.worksheet
+                        .write_string_with_format(self.row, col, &value, text_format)?;
+                    self.fit(col, value.chars().count());
+                }
+                (_, value) => {
+                    let value = truncate_cell(&value.to_string());
+                    self.worksheet
+                        .write_string_with_format(self.row, col, &value, text_format)?;
+                    self.fit(col, value.chars().count());
+                }
+            }
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    /// All the worksheets of the sheet, in order
+    fn into_worksheets(self) -> std::result::Result<Vec<Worksheet>, XlsxError> {
+        let mut worksheets = self.full;
+        worksheets.push(set_widths(self.worksheet, &self.widths)?);
+        Ok(worksheets)
+    }
+}
+
+fn set_widths(
+    mut worksheet: Worksheet,
+    widths: &[usize],
+) -> std::result::Result<Worksheet, XlsxError> {
+    for (col, width) in widths.iter().enumerate() {
+        worksheet.set_column_width(col as u16, *width as f64 + 2.0)?;
+    }
+    Ok(worksheet)
+}
+
+struct Formats {
+    header: Format,
+    text: Format,
+    rtl: Format,
+    datetime: Format,
+}
+
+/// Counts shown in the summary sheet
+#[derive(Default)]
+struct Summary {
+    tabs: u64,
+    saved_tabs: u64,
+    tabs_with_unsaved_chunks: u64,
+    unsaved_chunks: u64,
+    invalid_checksums: u64,
+    warnings: u64,
+    errors: u64,
+    directories: u64,
+}
+
+/// An Excel workbook with a summary sheet, a sheet for the tabs, the unsaved chunks, the lint
+/// warnings and the `TabState` directory summaries. The workbook is kept in memory and written to the output by `finish`.
+/// Sheets that reach Excel's row limit continue in a new sheet
+pub struct XlsxSink {
+    output: Box<dyn Write>,
+    formats: Formats,
+    tabs: Sheet,
+    chunks: Sheet,
+    lint: Sheet,
+    directories: Sheet,
+    summary: Summary,
+}
+
+impl XlsxSink {
+    pub fn new(output: Box<dyn Write>) -> std::result::Result<Self, NotepadErrors> {
+        Self::with_max_rows(output, MAX_ROWS)
+    }
+
+    /// Sink with worksheets of `max_rows` rows, smaller than Excel's limit in the tests
+    pub(crate) fn with_max_rows(
+        output: Box<dyn Write>,
+        max_rows: u32,
+    ) -> std::result::Result<Self, NotepadErrors> {
+        let formats = Formats {
+            header: Format::new().set_bold(),
+            text: Format::new(),
+            rtl: Format::new().set_reading_direction(2),
+            datetime: Format::new().set_num_format(DATETIME_FORMAT),
+        };
+        let chunk = ChunkCsvRecord {
+            schema_version: SchemaVersion,
+            tabstate_path: None,
+            tab_guid: None,
+            chunk_index: 0,
+            position: 0,
+            num_of_deletion: 0,
+            num_of_addition: 0,
+            data: None,
+            checksum: String::new(),
+            checksum_valid: None,
+            location: None,
+        };
+        let lint = LintRow {
+            tabstate_path: "",
+            tab_guid: None,
+            code: String::new(),
+            field: "",
+            message: String::new(),
+        };
+        let directory = SummaryCsvRecord::from(&TabStateSummary::from_records("", &[]));
+        Ok(Self {
+            tabs: Sheet::new(
+                "Tabs",
+                columns(&CsvRecord::from(&NotepadTabStat::default())),
+                max_rows,
+                &formats,
+            )?,
+            chunks: Sheet::new("Chunks", columns(&chunk), max_rows, &formats)?,
+            lint: Sheet::new("Lint", columns(&lint), max_rows, &formats)?,
+            directories: Sheet::new("Directories", columns(&directory), max_rows, &formats)?,
+            output,
+            formats,
+            summary: Summary::default(),
+        })
+    }
+
+    /// Names of the worksheets written so far, the summary sheet is only created by `finish`
+    #[cfg(test)]
+    pub(crate) fn worksheet_names(&self) -> Vec<String> {
+        [&self.tabs, &self.chunks, &self.lint, &self.directories]
+            .into_iter()
+            .flat_map(|sheet| sheet.full.iter().chain([&sheet.worksheet]))
+            .map(|worksheet| worksheet.name())
+            .collect()
+    }
+
+    fn summary_sheet(&self) -> std::result::Result<Sheet, NotepadErrors> {
+        #[derive(Serialize)]
+        struct SummaryRow<'a> {
+            item: &'a str,
+            value: Value,
+        }
+        let headers = columns(&SummaryRow {
+            item: "",
+            value: Value::Null,
+        });
+        let mut sheet = Sheet::new("Summary", headers, MAX_ROWS, &self.formats)?;
+        let summary = &self.summary;
+        let rows = [
+            ("Schema version", Value::from(SCHEMA_VERSION)),
+            ("Tabs", summary.tabs.into()),
+            ("Saved file tabs", summary.saved_tabs.into()),
+            ("Unsaved tabs", (summary.tabs - summary.saved_tabs).into()),
+            (
+                "Tabs with unsaved chunks",
+                summary.tabs_with_unsaved_chunks.into(),
+            ),
+            ("Unsaved chunks", summary.unsaved_chunks.into()),
+            (
+                "Chunks with invalid checksums",
+                summary.invalid_checksums.into(),
+            ),
+            ("Lint warnings", summary.warnings.into()),
+            ("Files failed to parse", summary.errors.into()),
+            ("TabState directories", summary.directories.into()),
+        ];
+        for (item, value) in rows {
+            sheet
+                .write(&SummaryRow { item, value }, &[], false, &self.formats)
+                .map_err(|e| xlsx_error(e, "Unable to write the summary"))?;
+        }
+        Ok(sheet)
+    }
+}
+
+impl OutputSink for XlsxSink {
+    fn record(&mut self, record: &NotepadTabStat) -> std::result::Result<(), NotepadErrors> {
+        let rtl = record.config_block.rtl;
+        let times = record.tabstate_times.as_ref();
+        let tab_times = [
+            ("last_write_time", record.last_write_time.as_ref()),
+            (
+                "tabstate_modified",
+                times.and_then(|times| times.modified.as_ref()),
+            ),
+            (
+                "tabstate_accessed",
+                times.and_then(|times| times.accessed.as_ref()),
+            ),
+            (
+                "tabstate_changed",
+                times.and_then(|times| times.changed.as_ref()),
+            ),
+            (
+                "tabstate_created",
+                times.and_then(|times| times.created.as_ref()),
+            ),
+        ];
+        self.tabs
+            .write(&CsvRecord::from(record), &tab_times, rtl, &self.formats)
+            .map_err(|e| xlsx_error(e, "Unable to write the tab row"))?;
+
+        let chunks = ChunkCsvRecord::from_record(record);
+        self.chunks
+            .reserve(chunks.len(), &self.formats)
+            .map_err(|e| xlsx_error(e, "Unable to create the chunks worksheet"))?;
+        for chunk in &chunks {
+            self.chunks
+                .write(chunk, &[], rtl, &self.formats)
+                .map_err(|e| xlsx_error(e, "Unable to write the chunk row"))?;
+        }
+
+        let tabstate_path = record.tabstate_path.as_deref().unwrap_or_default();
+        for warning in record.warnings.iter().flatten() {
+            let row = LintRow {
+                tabstate_path,
+                tab_guid: record.tab_guid(),
+                code: warning.code.to_string(),
+                field: &warning.field,
+                message: warning.message.clone(),
+            };
+            self.lint
+                .write(&row, &[], rtl, &self.formats)
+                .map_err(|e| xlsx_error(e, "Unable to write the lint row"))?;
+        }
+
+        let summary = &mut self.summary;
+        summary.tabs += 1;
+        summary.saved_tabs += record.is_saved_file as u64;
+        summary.tabs_with_unsaved_chunks += !chunks.is_empty() as u64;
+        summary.unsaved_chunks += chunks.len() as u64;
+        summary.invalid_checksums += chunks
+            .iter()
+            .filter(|chunk| chunk.checksum_valid == Some(false))
+            .count() as u64;
+        summary.warnings += record.warnings.as_ref().map_or(0, |w| w.len()) as u64;
+        Ok(())
+    }
+
+    fn summary(&mut self, summary: &TabStateSummary) -> std::result::Result<(), NotepadErrors> {
+        self.summary.directories += 1;
+        let last_activity = summary.last_activity.map(FileTime::from);
+        self.directories
+            .write(
+                &SummaryCsvRecord::from(summary),
+                &[("last_activity", last_activity.as_ref())],
+                false,
+                &self.formats,
+            )
+            .map_err(|e| xlsx_error(e, "Unable to write the summary row"))
+    }
+
+    /// Files that failed to parse are listed in the lint sheet
+    fn error(
+        &mut self,
+        path: &str,
+        error: &NotepadErrors,
+    ) -> std::result::Result<(), NotepadErrors> {
+        self.summary.errors += 1;
+        let row = LintRow {
+            tabstate_path: path,
+            tab_guid: None,
+            code: "parse_error".to_string(),
+            field: "",
+            message: error.to_string(),
+        };
+        self.lint
+            .write(&row, &[], false, &self.formats)
+            .map_err(|e| xlsx_error(e, "Unable to write the lint row"))
+    }
+
+    fn finish(&mut self) -> std::result::Result<(), NotepadErrors> {
+        let mut workbook = Workbook::new();
+        let mut worksheets = self
+            .summary_sheet()?
+            .into_worksheets()
+            .map_err(|e| xlsx_error(e, "Unable to write the summary"))?;
+        let sheets = [
+            ("Tabs", &mut self.tabs),
+            ("Chunks", &mut self.chunks),
+            ("Lint", &mut self.lint),
+            ("Directories", &mut self.directories),
+        ];
+        for (name, sheet) in sheets {
+            // The directory summaries are only written with `--summary`
+            if name == "Directories" && sheet.is_empty() {
+                continue;
+            }
+            let empty = Sheet::new(name, vec![], MAX_ROWS, &self.formats)?;
+            worksheets.extend(
+                std::mem::replace(sheet, empty)
+                    .into_worksheets()
+                    .map_err(|e| xlsx_error(e, "Unable to write the worksheet"))?,
+            );
+        }
+        for worksheet in worksheets {
+            workbook.push_worksheet(worksheet);
+        }
+
+        let buffer = workbook
+            .save_to_buffer()
+            .map_err(|e| xlsx_error(e, "Unable to create the workbook"))?;
+        self.output
+            .write_all(&buffer)
+            .and_then(|_| self.output.flush())
+            .map_err(|e| {
+                NotepadErrors::Output(e.to_string(), "Unable to write the workbook".to_string())
+            })
+    }
+}